@@ -0,0 +1,106 @@
+use super::{ScoreType, SearchLimits};
+
+use std::{fmt, str::FromStr, time::Duration};
+
+/// How strongly the AI plays
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Difficulty {
+    Beginner,
+    Easy,
+    Medium,
+    Hard,
+    /// No handicap at all
+    #[default]
+    Full,
+}
+
+/// The handicaps that make up a difficulty level
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Handicap {
+    /// The deepest the search goes, whatever else it is allowed
+    pub max_depth: Option<u8>,
+    /// The longest the search thinks about a move, whatever else it is allowed
+    pub max_time: Option<Duration>,
+    /// The evaluation of every position is off by up to this many centipawns either way
+    pub noise: ScoreType,
+    /// How many moves in a thousand are replaced by a worse one
+    pub blunders_per_mille: u32,
+    /// The most centipawns a blunder gives away, as far as a quick look can tell
+    pub max_blunder_loss: ScoreType,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 5] = [
+        Difficulty::Beginner,
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Full,
+    ];
+
+    pub fn handicap(self) -> Handicap {
+        let (max_depth, max_time, noise, blunders_per_mille, max_blunder_loss) = match self {
+            Difficulty::Beginner => (Some(1), Some(100), 150, 300, 900),
+            Difficulty::Easy => (Some(2), Some(250), 80, 150, 400),
+            Difficulty::Medium => (Some(3), Some(500), 40, 60, 200),
+            Difficulty::Hard => (Some(5), Some(1000), 15, 20, 100),
+            Difficulty::Full => (None, None, 0, 0, 0),
+        };
+        Handicap {
+            max_depth,
+            max_time: max_time.map(Duration::from_millis),
+            noise,
+            blunders_per_mille,
+            max_blunder_loss,
+        }
+    }
+
+    /// The level after this one, going back to the easiest after full strength
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&level| level == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+impl Handicap {
+    /// `limits`, tightened to the depth and time this handicap allows
+    pub fn limit(&self, limits: SearchLimits) -> SearchLimits {
+        fn tighter<T: Ord>(limit: Option<T>, cap: Option<T>) -> Option<T> {
+            match (limit, cap) {
+                (Some(limit), Some(cap)) => Some(limit.min(cap)),
+                (limit, cap) => limit.or(cap),
+            }
+        }
+        SearchLimits {
+            depth: tighter(limits.depth, self.max_depth),
+            move_time: tighter(limits.move_time, self.max_time),
+            ..limits
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Difficulty::Beginner => "beginner",
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+            Difficulty::Full => "full",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    /// Reads the names written by `Display`, e.g. `medium`
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|level| level.to_string() == text.to_lowercase())
+            .ok_or_else(|| format!("Unknown difficulty {}", text))
+    }
+}
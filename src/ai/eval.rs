@@ -0,0 +1,605 @@
+//! Static evaluation of a position, in centipawns.
+//!
+//! Every term is computed separately for the middlegame and the endgame,
+//! then blended by how much material is left on the board.
+
+use super::ScoreType;
+
+use chess::{
+    get_adjacent_files, get_bishop_moves, get_file, get_king_moves, get_knight_moves,
+    get_rook_moves, BitBoard, Board, Color, Piece, Square, ALL_FILES, EMPTY,
+};
+
+use std::{
+    convert::TryFrom,
+    ops::{Add, AddAssign, Mul, Neg, Sub},
+};
+
+/// A score split into its middlegame and endgame parts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Tapered {
+    pub mg: ScoreType,
+    pub eg: ScoreType,
+}
+
+impl Tapered {
+    pub const fn new(mg: ScoreType, eg: ScoreType) -> Self {
+        Self { mg, eg }
+    }
+
+    /// Blends the two parts, where `phase` goes from 0 (bare kings) to `MAX_PHASE`
+    pub fn taper(self, phase: ScoreType) -> ScoreType {
+        (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl Add for Tapered {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.mg + rhs.mg, self.eg + rhs.eg)
+    }
+}
+
+impl AddAssign for Tapered {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Tapered {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl Neg for Tapered {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self::new(-self.mg, -self.eg)
+    }
+}
+
+impl Mul<ScoreType> for Tapered {
+    type Output = Self;
+    fn mul(self, rhs: ScoreType) -> Self {
+        Self::new(self.mg * rhs, self.eg * rhs)
+    }
+}
+
+/// The phase of the starting position
+pub const MAX_PHASE: ScoreType = 24;
+
+const PIECES: [Piece; 6] = [
+    Piece::Pawn,
+    Piece::Knight,
+    Piece::Bishop,
+    Piece::Rook,
+    Piece::Queen,
+    Piece::King,
+];
+
+/// A rough value of each piece, used where a single number is needed (e.g. move ordering)
+pub const fn piece_value(piece: Piece) -> ScoreType {
+    match piece {
+        Piece::Pawn => 100,
+        Piece::Knight => 320,
+        Piece::Bishop => 330,
+        Piece::Rook => 500,
+        Piece::Queen => 900,
+        // Never actually captured, but sorts king moves after everything else
+        Piece::King => 20_000,
+    }
+}
+
+const fn material(piece: Piece) -> Tapered {
+    match piece {
+        Piece::Pawn => Tapered::new(82, 94),
+        Piece::Knight => Tapered::new(337, 281),
+        Piece::Bishop => Tapered::new(365, 297),
+        Piece::Rook => Tapered::new(477, 512),
+        Piece::Queen => Tapered::new(1025, 936),
+        Piece::King => Tapered::new(0, 0),
+    }
+}
+
+/// How much each piece contributes to the game phase
+const fn phase_weight(piece: Piece) -> ScoreType {
+    match piece {
+        Piece::Knight | Piece::Bishop => 1,
+        Piece::Rook => 2,
+        Piece::Queen => 4,
+        Piece::Pawn | Piece::King => 0,
+    }
+}
+
+// Piece-square tables, laid out as seen from White's side:
+// the first row is the 8th rank and the last row is the 1st rank.
+
+#[rustfmt::skip]
+const PAWN_MG: [ScoreType; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    10,  10,  20,  30,  30,  20,  10,  10,
+     5,   5,  10,  25,  25,  10,   5,   5,
+     0,   0,   0,  20,  20,   0,   0,   0,
+     5,  -5, -10,   0,   0, -10,  -5,   5,
+     5,  10,  10, -20, -20,  10,  10,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const PAWN_EG: [ScoreType; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    80,  80,  80,  80,  80,  80,  80,  80,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    30,  30,  30,  30,  30,  30,  30,  30,
+    20,  20,  20,  20,  20,  20,  20,  20,
+    10,  10,  10,  10,  10,  10,  10,  10,
+     5,   5,   5,   5,   5,   5,   5,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_MG: [ScoreType; 64] = [
+   -50, -40, -30, -30, -30, -30, -40, -50,
+   -40, -20,   0,   0,   0,   0, -20, -40,
+   -30,   0,  10,  15,  15,  10,   0, -30,
+   -30,   5,  15,  20,  20,  15,   5, -30,
+   -30,   0,  15,  20,  20,  15,   0, -30,
+   -30,   5,  10,  15,  15,  10,   5, -30,
+   -40, -20,   0,   5,   5,   0, -20, -40,
+   -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const KNIGHT_EG: [ScoreType; 64] = [
+   -50, -40, -30, -30, -30, -30, -40, -50,
+   -40, -20,   0,   5,   5,   0, -20, -40,
+   -30,   5,  10,  15,  15,  10,   5, -30,
+   -30,   5,  15,  20,  20,  15,   5, -30,
+   -30,   5,  15,  20,  20,  15,   5, -30,
+   -30,   5,  10,  15,  15,  10,   5, -30,
+   -40, -20,   0,   5,   5,   0, -20, -40,
+   -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const BISHOP_MG: [ScoreType; 64] = [
+   -20, -10, -10, -10, -10, -10, -10, -20,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -10,   0,   5,  10,  10,   5,   0, -10,
+   -10,   5,   5,  10,  10,   5,   5, -10,
+   -10,   0,  10,  10,  10,  10,   0, -10,
+   -10,  10,  10,  10,  10,  10,  10, -10,
+   -10,   5,   0,   0,   0,   0,   5, -10,
+   -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const BISHOP_EG: [ScoreType; 64] = [
+   -20, -10, -10, -10, -10, -10, -10, -20,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -10,   0,  10,  10,  10,  10,   0, -10,
+   -10,   0,  10,  15,  15,  10,   0, -10,
+   -10,   0,  10,  15,  15,  10,   0, -10,
+   -10,   0,  10,  10,  10,  10,   0, -10,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const ROOK_MG: [ScoreType; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+     5,  10,  10,  10,  10,  10,  10,   5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+     0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const ROOK_EG: [ScoreType; 64] = [
+    10,  10,  10,  10,  10,  10,  10,  10,
+    15,  15,  15,  15,  15,  15,  15,  15,
+     5,   5,   5,   5,   5,   5,   5,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const QUEEN_MG: [ScoreType; 64] = [
+   -20, -10, -10,  -5,  -5, -10, -10, -20,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -10,   0,   5,   5,   5,   5,   0, -10,
+    -5,   0,   5,   5,   5,   5,   0,  -5,
+     0,   0,   5,   5,   5,   5,   0,  -5,
+   -10,   5,   5,   5,   5,   5,   0, -10,
+   -10,   0,   5,   0,   0,   0,   0, -10,
+   -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const QUEEN_EG: [ScoreType; 64] = [
+   -20, -10, -10,  -5,  -5, -10, -10, -20,
+   -10,   0,   5,   5,   5,   5,   0, -10,
+   -10,   5,  10,  10,  10,  10,   5, -10,
+    -5,   5,  10,  15,  15,  10,   5,  -5,
+    -5,   5,  10,  15,  15,  10,   5,  -5,
+   -10,   5,  10,  10,  10,  10,   5, -10,
+   -10,   0,   5,   5,   5,   5,   0, -10,
+   -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const KING_MG: [ScoreType; 64] = [
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -20, -30, -30, -40, -40, -30, -30, -20,
+   -10, -20, -20, -20, -20, -20, -20, -10,
+    20,  20,   0,   0,   0,   0,  20,  20,
+    20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+#[rustfmt::skip]
+const KING_EG: [ScoreType; 64] = [
+   -50, -40, -30, -20, -20, -30, -40, -50,
+   -30, -20, -10,   0,   0, -10, -20, -30,
+   -30, -10,  20,  30,  30,  20, -10, -30,
+   -30, -10,  30,  40,  40,  30, -10, -30,
+   -30, -10,  30,  40,  40,  30, -10, -30,
+   -30, -10,  20,  30,  30,  20, -10, -30,
+   -30, -30,   0,   0,   0,   0, -30, -30,
+   -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+const fn piece_square_tables(piece: Piece) -> (&'static [ScoreType; 64], &'static [ScoreType; 64]) {
+    match piece {
+        Piece::Pawn => (&PAWN_MG, &PAWN_EG),
+        Piece::Knight => (&KNIGHT_MG, &KNIGHT_EG),
+        Piece::Bishop => (&BISHOP_MG, &BISHOP_EG),
+        Piece::Rook => (&ROOK_MG, &ROOK_EG),
+        Piece::Queen => (&QUEEN_MG, &QUEEN_EG),
+        Piece::King => (&KING_MG, &KING_EG),
+    }
+}
+
+/// Index into a piece-square table for a piece of `color` on `square`
+fn table_index(square: Square, color: Color) -> usize {
+    match color {
+        // The tables start at a8, so White has to flip the rank
+        Color::White => square.to_index() ^ 56,
+        Color::Black => square.to_index(),
+    }
+}
+
+const DOUBLED_PAWN: Tapered = Tapered::new(-10, -20);
+const ISOLATED_PAWN: Tapered = Tapered::new(-10, -15);
+/// Bonus for a passed pawn by how many ranks it has advanced
+const PASSED_PAWN: [Tapered; 8] = [
+    Tapered::new(0, 0),
+    Tapered::new(5, 10),
+    Tapered::new(10, 20),
+    Tapered::new(15, 35),
+    Tapered::new(25, 60),
+    Tapered::new(40, 100),
+    Tapered::new(60, 150),
+    Tapered::new(0, 0),
+];
+
+/// Bonus per friendly pawn in front of a king
+const PAWN_SHIELD: ScoreType = 10;
+/// Penalty per attack on the squares around a king, scaled by the attacker
+const KING_ZONE_ATTACK: ScoreType = 5;
+
+/// Scores the board from the point of view of `player`
+pub fn evaluate(board: &Board, player: Color) -> ScoreType {
+    let white = side_score(board, Color::White);
+    let black = side_score(board, Color::Black);
+    let score = (white - black).taper(game_phase(board));
+
+    match player {
+        Color::White => score,
+        Color::Black => -score,
+    }
+}
+
+/// Every evaluation term for one side
+fn side_score(board: &Board, color: Color) -> Tapered {
+    material_and_placement(board, color)
+        + pawn_structure(board, color)
+        + mobility(board, color)
+        + king_safety(board, color)
+}
+
+/// How far from the endgame the position is, from 0 (only pawns and kings) to `MAX_PHASE`
+pub fn game_phase(board: &Board) -> ScoreType {
+    let phase: ScoreType = PIECES
+        .iter()
+        .map(|&piece| phase_weight(piece) * count(*board.pieces(piece)))
+        .sum();
+
+    // Promotions can push the phase past the starting position
+    phase.min(MAX_PHASE)
+}
+
+/// Material plus piece-square table bonuses for `color`
+pub fn material_and_placement(board: &Board, color: Color) -> Tapered {
+    let mut score = Tapered::default();
+    for &piece in PIECES.iter() {
+        let (mg_table, eg_table) = piece_square_tables(piece);
+        for square in *board.pieces(piece) & board.color_combined(color) {
+            let index = table_index(square, color);
+            score += material(piece) + Tapered::new(mg_table[index], eg_table[index]);
+        }
+    }
+    score
+}
+
+/// Penalties for doubled and isolated pawns, bonuses for passed pawns
+pub fn pawn_structure(board: &Board, color: Color) -> Tapered {
+    let pawns = board.pieces(Piece::Pawn);
+    let own_pawns = *pawns & board.color_combined(color);
+    let their_pawns = *pawns & board.color_combined(!color);
+
+    let mut score = Tapered::default();
+
+    for &file in ALL_FILES.iter() {
+        let on_file = count(own_pawns & get_file(file));
+        if on_file > 1 {
+            score += DOUBLED_PAWN * (on_file - 1);
+        }
+        if on_file > 0 && own_pawns & get_adjacent_files(file) == EMPTY {
+            score += ISOLATED_PAWN * on_file;
+        }
+    }
+
+    for square in own_pawns {
+        let file = square.get_file();
+        let blockers = (get_file(file) | get_adjacent_files(file)) & ranks_ahead(square, color);
+        if their_pawns & blockers == EMPTY {
+            score += PASSED_PAWN[relative_rank(square, color)];
+        }
+    }
+
+    score
+}
+
+/// Bonus for the number of squares each minor and major piece can move to,
+/// relative to a typical count for that piece
+pub fn mobility(board: &Board, color: Color) -> Tapered {
+    let occupied = *board.combined();
+    let targets = !board.color_combined(color);
+
+    let mut score = Tapered::default();
+    for &(piece, baseline, weight) in [
+        (Piece::Knight, 4, Tapered::new(4, 4)),
+        (Piece::Bishop, 6, Tapered::new(5, 5)),
+        (Piece::Rook, 7, Tapered::new(2, 4)),
+        (Piece::Queen, 13, Tapered::new(1, 2)),
+    ]
+    .iter()
+    {
+        for square in *board.pieces(piece) & board.color_combined(color) {
+            let moves = attacks(piece, square, occupied) & targets;
+            score += weight * (count(moves) - baseline);
+        }
+    }
+    score
+}
+
+/// Bonus for pawns sheltering the king and a penalty for enemy pieces attacking around it.
+/// Only matters in the middlegame, where there is enough material left to mount an attack.
+pub fn king_safety(board: &Board, color: Color) -> Tapered {
+    let king = board.king_square(color);
+    let zone = get_king_moves(king) | BitBoard::from_square(king);
+
+    let own_pawns = *board.pieces(Piece::Pawn) & board.color_combined(color);
+    let shield_files = get_file(king.get_file()) | get_adjacent_files(king.get_file());
+    let shield = own_pawns & shield_files & ranks_ahead(king, color) & near_ranks(king, color);
+
+    let occupied = *board.combined();
+    let mut attack_units = 0;
+    for &(piece, weight) in [
+        (Piece::Knight, 2),
+        (Piece::Bishop, 2),
+        (Piece::Rook, 3),
+        (Piece::Queen, 5),
+    ]
+    .iter()
+    {
+        for square in *board.pieces(piece) & board.color_combined(!color) {
+            attack_units += weight * count(attacks(piece, square, occupied) & zone);
+        }
+    }
+
+    Tapered::new(
+        count(shield) * PAWN_SHIELD - attack_units * KING_ZONE_ATTACK,
+        0,
+    )
+}
+
+fn attacks(piece: Piece, square: Square, occupied: BitBoard) -> BitBoard {
+    match piece {
+        Piece::Knight => get_knight_moves(square),
+        Piece::Bishop => get_bishop_moves(square, occupied),
+        Piece::Rook => get_rook_moves(square, occupied),
+        Piece::Queen => get_bishop_moves(square, occupied) | get_rook_moves(square, occupied),
+        Piece::King => get_king_moves(square),
+        Piece::Pawn => unreachable!("Pawn attacks depend on color"),
+    }
+}
+
+/// The rank of `square` counted from `color`'s side of the board, from 0 to 7
+fn relative_rank(square: Square, color: Color) -> usize {
+    match color {
+        Color::White => square.get_rank().to_index(),
+        Color::Black => 7 - square.get_rank().to_index(),
+    }
+}
+
+/// Every square on a rank in front of `square`, from `color`'s point of view
+fn ranks_ahead(square: Square, color: Color) -> BitBoard {
+    let rank = square.get_rank().to_index();
+    match color {
+        Color::White if rank == 7 => EMPTY,
+        Color::White => BitBoard::new(!0 << (8 * (rank + 1))),
+        Color::Black => BitBoard::new((1 << (8 * rank)) - 1),
+    }
+}
+
+/// The two ranks in front of `square`, from `color`'s point of view
+fn near_ranks(square: Square, color: Color) -> BitBoard {
+    let rank = square.get_rank().to_index();
+    let rank_mask = |rank: usize| BitBoard::new(0xFF << (8 * rank));
+    match color {
+        Color::White => (rank + 1..=(rank + 2).min(7)).fold(EMPTY, |acc, r| acc | rank_mask(r)),
+        Color::Black => (rank.saturating_sub(2)..rank).fold(EMPTY, |acc, r| acc | rank_mask(r)),
+    }
+}
+
+fn count(bitboard: BitBoard) -> ScoreType {
+    ScoreType::try_from(bitboard.popcnt()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    fn board(fen: &str) -> Board {
+        Board::from_str(fen).unwrap()
+    }
+
+    /// The same position with the colors swapped and the board flipped top to bottom
+    fn mirror(fen: &str) -> String {
+        let fields: Vec<_> = fen.split_whitespace().collect();
+        let swap_case = |c: char| {
+            if c.is_ascii_uppercase() {
+                c.to_ascii_lowercase()
+            } else {
+                c.to_ascii_uppercase()
+            }
+        };
+
+        let placement: Vec<String> = fields[0]
+            .split('/')
+            .rev()
+            .map(|rank| rank.chars().map(swap_case).collect())
+            .collect();
+        let side = if fields[1] == "w" { "b" } else { "w" };
+        let mut castling: Vec<char> = fields[2].chars().map(swap_case).collect();
+        // White's rights come first
+        castling.sort_by_key(|c| (c.is_ascii_lowercase(), *c != 'K' && *c != 'k'));
+        let en_passant = match fields[3] {
+            "-" => "-".to_string(),
+            square => square.replace('3', "x").replace('6', "3").replace('x', "6"),
+        };
+        format!(
+            "{} {} {} {} {} {}",
+            placement.join("/"),
+            side,
+            castling.into_iter().collect::<String>(),
+            en_passant,
+            fields[4],
+            fields[5]
+        )
+    }
+
+    #[test]
+    fn evaluation_is_color_symmetric() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "6k1/5ppp/8/8/8/8/2q2PPP/6K1 b - - 0 1",
+        ]
+        .iter()
+        {
+            let original = board(fen);
+            let mirrored = board(&mirror(fen));
+            assert_eq!(
+                evaluate(&original, Color::White),
+                evaluate(&mirrored, Color::Black),
+                "{}",
+                fen
+            );
+            assert_eq!(
+                evaluate(&original, Color::White),
+                -evaluate(&original, Color::Black)
+            );
+        }
+    }
+
+    #[test]
+    fn doubled_pawns_are_penalized() {
+        // The a-pawns are doubled, and the black pawns stop them from being passed
+        let board = board("4k3/pp6/8/8/8/P7/PP6/4K3 w - - 0 1");
+        assert_eq!(pawn_structure(&board, Color::White), DOUBLED_PAWN);
+        assert_eq!(pawn_structure(&board, Color::Black), Tapered::default());
+    }
+
+    #[test]
+    fn isolated_pawns_are_penalized() {
+        let board = board("4k3/p7/8/8/8/8/P7/4K3 w - - 0 1");
+        assert_eq!(pawn_structure(&board, Color::White), ISOLATED_PAWN);
+        assert_eq!(pawn_structure(&board, Color::Black), ISOLATED_PAWN);
+    }
+
+    #[test]
+    fn passed_pawns_get_a_bonus_by_rank() {
+        let passed = board("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1");
+        let blocked = board("4k3/3p4/8/3P4/8/8/8/4K3 w - - 0 1");
+        assert_eq!(
+            pawn_structure(&passed, Color::White) - pawn_structure(&blocked, Color::White),
+            PASSED_PAWN[4]
+        );
+
+        let black_passed = board("4k3/8/8/8/3p4/8/8/4K3 w - - 0 1");
+        assert_eq!(
+            pawn_structure(&black_passed, Color::Black),
+            ISOLATED_PAWN + PASSED_PAWN[4]
+        );
+    }
+
+    #[test]
+    fn game_phase_goes_from_start_to_bare_kings() {
+        assert_eq!(game_phase(&Board::default()), MAX_PHASE);
+        assert_eq!(game_phase(&board("4k3/8/8/8/8/8/8/4K3 w - - 0 1")), 0);
+        assert_eq!(
+            game_phase(&board("4k3/pppp4/8/8/8/8/PPPP4/R3K3 w - - 0 1")),
+            2
+        );
+    }
+
+    #[test]
+    fn king_safety_rewards_a_pawn_shield() {
+        let shielded = board("6k1/8/8/8/8/8/5PPP/6K1 w - - 0 1");
+        assert_eq!(
+            king_safety(&shielded, Color::White),
+            Tapered::new(3 * PAWN_SHIELD, 0)
+        );
+
+        let bare = board("6k1/8/8/8/8/8/8/6K1 w - - 0 1");
+        assert_eq!(king_safety(&bare, Color::White), Tapered::default());
+    }
+
+    #[test]
+    fn king_safety_penalizes_attacks_near_the_king() {
+        // The knight hits g1 and h2, next to the king
+        let attacked = board("6k1/8/8/8/8/5n2/8/6K1 w - - 0 1");
+        assert_eq!(
+            king_safety(&attacked, Color::White),
+            Tapered::new(-2 * 2 * KING_ZONE_ATTACK, 0)
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Moves assumed to be left in the game when the time control does not say
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// Time kept back from every move to cover communication and scheduling delays
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+
+/// How often (in nodes) the clock is checked, since reading it is not free
+const TIME_CHECK_INTERVAL: u64 = 1024;
+
+/// Constraints on how long a search may run.
+/// Any combination of limits can be set; the search stops at whichever is hit first.
+/// With no limits set, the search runs until it is stopped externally.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub move_time: Option<Duration>,
+    pub nodes: Option<u64>,
+}
+
+impl SearchLimits {
+    pub fn move_time(move_time: Duration) -> Self {
+        Self {
+            move_time: Some(move_time),
+            ..Default::default()
+        }
+    }
+
+    /// Budgets the time for one move from the time left on the player's clock
+    pub fn from_clock(remaining: Duration, increment: Duration, moves_to_go: Option<u32>) -> Self {
+        let moves_to_go = moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let usable = remaining.saturating_sub(MOVE_OVERHEAD);
+        // Divided first, since huge increments would overflow when multiplied
+        let budget = (usable / moves_to_go).saturating_add(increment / 4 * 3);
+
+        // Never bet more than half of what is left on a single move
+        Self::move_time(budget.min(usable / 2))
+    }
+
+    /// Searches until the stop flag is set
+    pub fn infinite() -> Self {
+        Default::default()
+    }
+}
+
+/// Tracks a running search against its limits
+pub(super) struct SearchControl<'a> {
+    stop: &'a AtomicBool,
+    start: Instant,
+    limits: SearchLimits,
+    pub nodes: u64,
+    aborted: bool,
+}
+
+impl<'a> SearchControl<'a> {
+    pub fn new(limits: SearchLimits, stop: &'a AtomicBool) -> Self {
+        Self {
+            stop,
+            start: Instant::now(),
+            limits,
+            nodes: 0,
+            aborted: false,
+        }
+    }
+
+    /// Counts a visited node and checks whether the search has to stop.
+    /// Once this returns `true`, it keeps returning `true`.
+    pub fn visit_node(&mut self) -> bool {
+        self.nodes += 1;
+
+        if !self.aborted {
+            let out_of_nodes = self.limits.nodes.is_some_and(|max| self.nodes > max);
+            let out_of_time = self.nodes % TIME_CHECK_INTERVAL == 0
+                && self
+                    .limits
+                    .move_time
+                    .is_some_and(|max| self.start.elapsed() >= max);
+            self.aborted = out_of_nodes || out_of_time || self.stop.load(Ordering::Relaxed);
+        }
+
+        self.aborted
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.aborted
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Whether there is enough time and depth left to start another iteration
+    pub fn can_start_iteration(&self, depth: u8) -> bool {
+        // The next iteration usually takes longer than all previous ones combined,
+        // so there is no point starting it past the halfway mark
+        let has_time = self
+            .limits
+            .move_time
+            .map_or(true, |max| self.elapsed() < max / 2);
+        let has_depth = self.limits.depth.map_or(true, |max| depth <= max);
+        !self.aborted && has_time && has_depth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(remaining: u64, increment: u64, moves_to_go: Option<u32>) -> Option<Duration> {
+        SearchLimits::from_clock(
+            Duration::from_millis(remaining),
+            Duration::from_millis(increment),
+            moves_to_go,
+        )
+        .move_time
+    }
+
+    #[test]
+    fn clock_budgets() {
+        // 59.95 seconds after the overhead, spread over 30 moves
+        assert_eq!(
+            budget(60_000, 0, None),
+            Some(Duration::from_nanos(1_998_333_333))
+        );
+        assert_eq!(
+            budget(60_000, 0, Some(10)),
+            Some(Duration::from_millis(5_995))
+        );
+        // Three quarters of the increment come on top
+        assert_eq!(
+            budget(60_000, 2_000, Some(10)),
+            Some(Duration::from_millis(7_495))
+        );
+    }
+
+    #[test]
+    fn clock_budgets_keep_half_the_time() {
+        assert_eq!(
+            budget(60_000, 0, Some(1)),
+            Some(Duration::from_millis(29_975))
+        );
+        assert_eq!(budget(1_000, 5_000, None), Some(Duration::from_millis(475)));
+        assert_eq!(budget(1_000, 0, Some(0)), Some(Duration::from_millis(475)));
+        assert_eq!(
+            SearchLimits::from_clock(Duration::from_secs(60), Duration::MAX, None).move_time,
+            Some(Duration::from_millis(29_975))
+        );
+    }
+
+    #[test]
+    fn no_time_left() {
+        assert_eq!(budget(40, 0, None), Some(Duration::ZERO));
+        assert_eq!(budget(0, 1_000, None), Some(Duration::ZERO));
+    }
+
+    /// Deeper than any search goes
+    const MAX_TEST_DEPTH: u8 = 100;
+
+    #[test]
+    fn stops_when_asked() {
+        let stop = AtomicBool::new(false);
+        let mut control = SearchControl::new(SearchLimits::infinite(), &stop);
+        assert!(!control.visit_node());
+        assert!(control.can_start_iteration(MAX_TEST_DEPTH));
+
+        stop.store(true, Ordering::Relaxed);
+        assert!(control.visit_node());
+        assert!(control.is_aborted());
+        assert!(!control.can_start_iteration(1));
+
+        // Stays stopped even if the flag is cleared
+        stop.store(false, Ordering::Relaxed);
+        assert!(control.visit_node());
+    }
+
+    #[test]
+    fn stops_after_the_node_limit() {
+        let stop = AtomicBool::new(false);
+        let limits = SearchLimits {
+            nodes: Some(10),
+            ..SearchLimits::default()
+        };
+        let mut control = SearchControl::new(limits, &stop);
+        for _ in 0..10 {
+            assert!(!control.visit_node());
+        }
+        assert!(control.visit_node());
+        assert!(control.visit_node());
+        assert_eq!(control.nodes, 12);
+    }
+
+    #[test]
+    fn checks_the_time_every_so_many_nodes() {
+        let stop = AtomicBool::new(false);
+        let mut control = SearchControl::new(SearchLimits::move_time(Duration::ZERO), &stop);
+        for _ in 1..TIME_CHECK_INTERVAL {
+            assert!(!control.visit_node());
+        }
+        assert!(control.visit_node());
+    }
+
+    #[test]
+    fn iterations_stop_at_the_depth_limit() {
+        let stop = AtomicBool::new(false);
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..SearchLimits::default()
+        };
+        let control = SearchControl::new(limits, &stop);
+        assert!(control.can_start_iteration(1));
+        assert!(control.can_start_iteration(3));
+        assert!(!control.can_start_iteration(4));
+    }
+
+    #[test]
+    fn iterations_stop_halfway_through_the_time() {
+        let stop = AtomicBool::new(false);
+        let control = SearchControl::new(SearchLimits::move_time(Duration::ZERO), &stop);
+        assert!(!control.can_start_iteration(1));
+
+        let control = SearchControl::new(SearchLimits::move_time(Duration::from_secs(60)), &stop);
+        assert!(control.can_start_iteration(1));
+    }
+}
@@ -1,135 +1,451 @@
-use std::collections::HashMap;
-use std::convert::TryFrom;
+use std::cmp::Reverse;
+use std::sync::{atomic::AtomicBool, Arc};
+use std::{fmt, time::Duration};
 
-use chess::{Board, BoardStatus, ChessMove, Color, MoveGen, Piece};
+use chess::{Board, BoardStatus, ChessMove, MoveGen};
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+pub mod book;
+use book::BookSettings;
+
+mod difficulty;
+pub use difficulty::{Difficulty, Handicap};
+
+pub mod eval;
+
+mod limits;
+use limits::SearchControl;
+pub use limits::SearchLimits;
+
+mod quiescence;
+
+pub mod tablebase;
+use tablebase::{Tablebase, Wdl};
+
+use quiescence::CHECK_PLIES;
+
+mod transposition;
+pub use transposition::DEFAULT_MEGABYTES as DEFAULT_HASH_MEGABYTES;
+use transposition::{Bound, TranspositionTable};
+
+mod worker;
+pub use worker::AIWorker;
 
 type ScoreType = isize;
 
-const DEPTH: u8 = 4;
+/// Iterative deepening never goes past this, even without a depth limit
+const MAX_DEPTH: u8 = 64;
+
+/// Score for delivering checkmate right now; mates further away score slightly less
+const MATE: ScoreType = 1_000_000;
+
+/// Any score this close to `MATE` is a forced mate rather than an evaluation
+const MATE_THRESHOLD: ScoreType = MATE - 1_000;
+
+/// Score for a position the tablebase says is won, below any mate the search finds itself
+const TB_WIN: ScoreType = MATE_THRESHOLD - 1_000;
 
-const fn points_for_piece(piece: Piece) -> ScoreType {
-    match piece {
-        Piece::Pawn => 1,
-        Piece::Knight | Piece::Bishop => 3,
-        Piece::Rook => 5,
-        Piece::Queen => 9,
-        Piece::King => 200,
+/// Larger than any score the search can return
+const INFINITY: ScoreType = MATE + 1;
+
+/// Converts a score into the number of moves (not plies) until mate,
+/// positive if the side to move is the one delivering it
+pub fn mate_in(score: ScoreType) -> Option<ScoreType> {
+    if score > MATE_THRESHOLD {
+        Some((MATE - score + 1) / 2)
+    } else if score < -MATE_THRESHOLD {
+        Some(-(MATE + score) / 2)
+    } else {
+        None
     }
 }
 
-fn guess_score(player: Color, board: Board) -> ScoreType {
-    let mut score = 0;
+/// Progress of a search, reported after every completed iteration
+#[derive(Clone, Debug)]
+pub struct SearchInfo {
+    pub depth: u8,
+    /// Centipawns from the point of view of the side to move
+    pub score: ScoreType,
+    pub nodes: u64,
+    pub time: Duration,
+    /// How full the transposition table is, in permille
+    pub hashfull: usize,
+    /// The expected line of play, starting with the best move
+    pub pv: Vec<ChessMove>,
+}
 
-    // Pieces on board
-    for piece in [
-        Piece::Pawn,
-        Piece::Knight,
-        Piece::Bishop,
-        Piece::Rook,
-        Piece::Queen,
-        Piece::King,
-    ] {
-        let pieces_of_player = board.color_combined(player) & board.pieces(piece);
-        let pieces_of_opponent = board.color_combined(!player) & board.pieces(piece);
-        score += points_for_piece(piece) * ScoreType::try_from(pieces_of_player.popcnt()).unwrap();
-        score -=
-            points_for_piece(piece) * ScoreType::try_from(pieces_of_opponent.popcnt()).unwrap();
+impl fmt::Display for SearchInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Depth {}: score {} after {} positions in {:?} (table {}/1000 full)",
+            self.depth, self.score, self.nodes, self.time, self.hashfull
+        )
     }
+}
 
-    // Giving check to king
-    let checkers_value = ScoreType::try_from(board.checkers().popcnt()).unwrap() * 10;
-
-    // Pinning something to the king
-    let pin_value = ScoreType::try_from(board.pinned().popcnt()).unwrap() * 5;
+/// What `AIState::best_move` has to say while choosing a move
+#[derive(Clone, Debug)]
+pub enum Progress {
+    /// The move comes from the opening book, without a search
+    Book(ChessMove),
+    /// An iteration of the search has finished
+    Iteration(SearchInfo),
+}
 
-    if player != board.side_to_move() {
-        score += checkers_value + pin_value;
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Progress::Book(chess_move) => write!(f, "Playing {} from the book", chess_move),
+            Progress::Iteration(info) => write!(f, "{}", info),
+        }
     }
+}
 
-    score
+pub struct AIState {
+    /// Results of earlier searches, shared across moves of a game
+    transposition_table: TranspositionTable,
+    /// Consulted before searching, while the game is young enough
+    book: Option<BookSettings>,
+    /// Picks between book moves and decides when to blunder
+    rng: StdRng,
+    /// Gives exact results for positions with few pieces left
+    tablebase: Option<Arc<Tablebase>>,
+    difficulty: Difficulty,
+    /// Mixed into position hashes to get the evaluation noise,
+    /// so a position is always off by the same amount within a game
+    noise_key: u64,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
-struct CacheEntry {
-    chess_move: ChessMove,
-    score: ScoreType,
-    color: Color,
-    eval_depth: u8,
+impl Default for AIState {
+    fn default() -> Self {
+        Self::with_hash_size(DEFAULT_HASH_MEGABYTES)
+    }
 }
 
-impl CacheEntry {
-    fn update_if_better(&mut self, new_entry: Self) {
-        if *self == new_entry {
-            return;
+impl AIState {
+    /// Creates a state whose transposition table uses about `megabytes` of memory
+    pub fn with_hash_size(megabytes: usize) -> Self {
+        let mut rng = StdRng::from_entropy();
+        Self {
+            transposition_table: TranspositionTable::with_megabytes(megabytes),
+            book: None,
+            noise_key: rng.gen(),
+            rng,
+            tablebase: None,
+            difficulty: Difficulty::default(),
         }
+    }
 
-        // Always pick the deeper eval
-        match self.eval_depth.cmp(&new_entry.eval_depth) {
-            std::cmp::Ordering::Less => {
-                *self = new_entry;
-                return;
+    /// Makes every random choice from now on (book moves, noise and blunders) follow from `seed`,
+    /// so the same seed and the same searches give the same moves.
+    /// Searches limited by time can still differ, since they reach different depths.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.noise_key = self.rng.gen();
+        // Scores stored with the old noise would not match the new one
+        self.transposition_table.clear();
+    }
+
+    /// Plays at `difficulty` from now on
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        if difficulty != self.difficulty {
+            self.difficulty = difficulty;
+            self.transposition_table.clear();
+        }
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// Replaces the transposition table with an empty one of about `megabytes`,
+    /// keeping the book and tablebase
+    pub fn resize_hash(&mut self, megabytes: usize) {
+        self.transposition_table = TranspositionTable::with_megabytes(megabytes);
+    }
+
+    /// Looks endgames up in `tablebase` from now on, or only searches with `None`
+    pub fn set_tablebase(&mut self, tablebase: Option<Arc<Tablebase>>) {
+        self.tablebase = tablebase;
+    }
+
+    /// Plays from `book` in the opening from now on, or only searches with `None`
+    pub fn set_book(&mut self, book: Option<BookSettings>) {
+        self.book = book;
+    }
+
+    /// A move from the book for `board`, if there is a book and `ply`
+    /// (the number of moves played since the initial position) is within its depth
+    pub fn book_move(&mut self, board: &Board, ply: usize) -> Option<ChessMove> {
+        let settings = self
+            .book
+            .as_ref()
+            .filter(|settings| ply < settings.max_plies)?;
+        settings.book.pick(board, &mut self.rng)
+    }
+
+    /// Forgets everything learned in previous searches, e.g. when a new game starts
+    pub fn clear(&mut self) {
+        self.transposition_table.clear();
+    }
+
+    /// Plays from the book if it has a move for `board`, which is `ply` moves into the game.
+    /// Otherwise searches for the best move for the side to move by iterative deepening
+    /// until a limit is hit or `stop` is set.
+    /// Tells `report` where the move comes from as it goes.
+    /// Returns the best move of the last completed iteration,
+    /// or `None` if there are no legal moves.
+    pub fn best_move<F: FnMut(Progress)>(
+        &mut self,
+        board: Board,
+        ply: usize,
+        limits: SearchLimits,
+        stop: &AtomicBool,
+        mut report: F,
+    ) -> Option<ChessMove> {
+        if let Some(chess_move) = self.book_move(&board, ply) {
+            report(Progress::Book(chess_move));
+            return Some(chess_move);
+        }
+        self.search(board, limits, stop, |info| {
+            report(Progress::Iteration(info.clone()))
+        })
+    }
+
+    /// Like `best_move` for the side to move,
+    /// but hands the details of every completed iteration to `report`.
+    /// Below full difficulty, the limits are tightened and the move may be a blunder.
+    pub fn search<F: FnMut(&SearchInfo)>(
+        &mut self,
+        board: Board,
+        limits: SearchLimits,
+        stop: &AtomicBool,
+        mut report: F,
+    ) -> Option<ChessMove> {
+        if let Some((chess_move, wdl)) = self.tablebase_move(&board) {
+            report(&SearchInfo {
+                depth: 1,
+                score: wdl.score(0),
+                nodes: 0,
+                time: Duration::default(),
+                hashfull: self.transposition_table.hashfull(),
+                pv: vec![chess_move],
+            });
+            return Some(chess_move);
+        }
+
+        let handicap = self.difficulty.handicap();
+        self.transposition_table.new_search();
+        let mut control = SearchControl::new(handicap.limit(limits), stop);
+
+        let mut best_move = None;
+        let mut depth = 1;
+        while depth <= MAX_DEPTH && control.can_start_iteration(depth) {
+            let (chess_move, score) =
+                self.alpha_beta(board, depth, 0, -INFINITY, INFINITY, &mut control);
+            if control.is_aborted() {
+                break;
+            }
+
+            best_move = chess_move.or(best_move);
+            report(&SearchInfo {
+                depth,
+                score,
+                nodes: control.nodes,
+                time: control.elapsed(),
+                hashfull: self.transposition_table.hashfull(),
+                pv: self.principal_variation(board, depth),
+            });
+
+            // No point searching deeper once a forced mate has been found
+            if mate_in(score).is_some() {
+                break;
             }
-            std::cmp::Ordering::Equal => {}
-            std::cmp::Ordering::Greater => return,
+            depth += 1;
         }
 
-        assert_eq!(self.color, new_entry.color);
+        // Every move loses or no iteration finished, but we still have to play something
+        let best_move = best_move.or_else(|| MoveGen::new_legal(&board).next())?;
 
-        if self.score.abs() < new_entry.score.abs() {
-            *self = new_entry;
+        if self.rng.gen_range(0, 1000) < handicap.blunders_per_mille {
+            return self
+                .blunder(board, best_move, handicap.max_blunder_loss)
+                .or(Some(best_move));
         }
+        Some(best_move)
     }
-}
 
-#[derive(Default)]
-pub struct AIState {
-    /// Cache scores and the color that the score is used for
-    score_cache: HashMap<String, CacheEntry>,
-}
+    /// A random move other than `best_move` that a quick look says loses at most `max_loss`
+    /// compared to the best-looking move, or `None` if there is no such move
+    fn blunder(
+        &mut self,
+        board: Board,
+        best_move: ChessMove,
+        max_loss: ScoreType,
+    ) -> Option<ChessMove> {
+        let stop = AtomicBool::new(false);
+        let mut control = SearchControl::new(SearchLimits::infinite(), &stop);
+        let scores: Vec<_> = MoveGen::new_legal(&board)
+            .map(|chess_move| {
+                let next_board = board.make_move_new(chess_move);
+                let score = -self.quiescence(
+                    next_board,
+                    1,
+                    CHECK_PLIES,
+                    -INFINITY,
+                    INFINITY,
+                    &mut control,
+                );
+                (chess_move, score)
+            })
+            .collect();
 
-impl AIState {
-    pub fn best_move(&mut self, board: Board, player: Color) -> ChessMove {
-        let chess_move = self
-            .alpha_beta(board, DEPTH, ScoreType::MIN + 1, ScoreType::MAX, player)
-            .0
-            .unwrap();
-        println!("Evaluated {} positions", self.score_cache.len());
-        chess_move
+        let top = scores.iter().map(|&(_, score)| score).max()?;
+        let candidates: Vec<_> = scores
+            .into_iter()
+            .filter(|&(chess_move, score)| chess_move != best_move && top - score <= max_loss)
+            .map(|(chess_move, _)| chess_move)
+            .collect();
+        candidates.choose(&mut self.rng).copied()
+    }
+
+    /// How far off the evaluation of `board` is at the current difficulty:
+    /// random, but always the same for the same position within a game
+    fn noise(&self, board: &Board) -> ScoreType {
+        let range = self.difficulty.handicap().noise;
+        if range == 0 {
+            return 0;
+        }
+        // SplitMix64's finalizer spreads the bits of the hash over the whole number
+        let mut z = board.get_hash() ^ self.noise_key;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z % (2 * range as u64 + 1)) as ScoreType - range
+    }
+
+    /// The move the tablebase says is best for `board`, and the result it leads to:
+    /// the quickest way to a capture or pawn move that keeps a win,
+    /// or the slowest one when losing. `None` unless every move could be looked up.
+    fn tablebase_move(&self, board: &Board) -> Option<(ChessMove, Wdl)> {
+        let tablebase = self.tablebase.as_ref().filter(|tb| tb.covers(board))?;
+        tablebase.best_move(board)
+    }
+
+    /// Follows the best moves stored in the transposition table from `board`
+    fn principal_variation(&self, mut board: Board, max_length: u8) -> Vec<ChessMove> {
+        let mut pv = Vec::new();
+        while pv.len() < max_length.into() {
+            let chess_move = match self
+                .transposition_table
+                .get(board.get_hash())
+                .and_then(|entry| entry.chess_move)
+            {
+                // The entry could belong to another position with a colliding index
+                Some(chess_move) if board.legal(chess_move) => chess_move,
+                _ => break,
+            };
+            pv.push(chess_move);
+            board = board.make_move_new(chess_move);
+        }
+        pv
     }
 
     fn alpha_beta(
         &mut self,
         board: Board,
         depth: u8,
+        ply: u8,
         mut alpha: ScoreType,
         beta: ScoreType,
-        player: Color,
+        control: &mut SearchControl,
     ) -> (Option<ChessMove>, ScoreType) {
-        if depth == 0 || board.status() != BoardStatus::Ongoing {
-            return match (board.status(), self.score_cache.get(&board.to_string())) {
-                (BoardStatus::Stalemate, _) => (None, 0),
-                (BoardStatus::Checkmate, _) => (None, ScoreType::MIN + 1),
-                (BoardStatus::Ongoing, Some(entry)) => (Some(entry.chess_move), entry.score),
-                (BoardStatus::Ongoing, None) => (None, guess_score(player, board)),
-            };
+        if control.visit_node() {
+            // The result will be thrown away, so bail out as fast as possible
+            return (None, 0);
+        }
+
+        match board.status() {
+            BoardStatus::Stalemate => return (None, 0),
+            BoardStatus::Checkmate => return (None, -MATE + ScoreType::from(ply)),
+            BoardStatus::Ongoing => {}
+        }
+
+        let hash = board.get_hash();
+        let mut hash_move = None;
+        if let Some(entry) = self.transposition_table.get(hash) {
+            let score = entry.score(ply);
+            // Never cut off at the root, since that would lose the move to play
+            if entry.depth >= depth && ply > 0 {
+                let usable = match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => score >= beta,
+                    Bound::Upper => score <= alpha,
+                };
+                if usable {
+                    return (entry.chess_move, score);
+                }
+            }
+            hash_move = entry.chess_move;
+        }
+
+        // The root is left to `tablebase_move`, which also picks the move
+        if ply > 0 {
+            if let Some(wdl) = self
+                .tablebase
+                .as_ref()
+                .and_then(|tablebase| tablebase.probe_wdl(&board))
+            {
+                let score = wdl.score(ply);
+                self.transposition_table
+                    .store(hash, None, score, ply, MAX_DEPTH, Bound::Exact);
+                return (None, score);
+            }
+        }
+
+        if depth == 0 {
+            return (
+                None,
+                self.quiescence(board, ply, CHECK_PLIES, alpha, beta, control),
+            );
         }
 
         let mut moves: Vec<_> = MoveGen::new_legal(&board).collect();
 
-        moves
-            .sort_by_cached_key(|chess_move| guess_score(player, board.make_move_new(*chess_move)));
+        let player = board.side_to_move();
+        moves.sort_by_cached_key(|chess_move| {
+            Reverse(eval::evaluate(&board.make_move_new(*chess_move), player))
+        });
+
+        // The best move from a previous (usually shallower) search goes first
+        if let Some(index) =
+            hash_move.and_then(|hash_move| moves.iter().position(|&m| m == hash_move))
+        {
+            moves[..=index].rotate_right(1);
+        }
 
         let mut best_so_far: Option<ChessMove> = None;
         for chess_move in moves {
             let next_board = board.make_move_new(chess_move);
             let score = -self
-                .alpha_beta(next_board, depth - 1, -beta, -alpha, !player)
+                .alpha_beta(next_board, depth - 1, ply + 1, -beta, -alpha, control)
                 .1;
+            if control.is_aborted() {
+                // Do not let a partial search pollute the table
+                return (None, 0);
+            }
             if score >= beta {
-                if let Some(best_so_far) = best_so_far {
-                    self.update_cache(board.to_string(), best_so_far, beta, player, depth);
-                }
-                return (best_so_far, beta);
+                self.transposition_table.store(
+                    hash,
+                    Some(chess_move),
+                    beta,
+                    ply,
+                    depth,
+                    Bound::Lower,
+                );
+                return (Some(chess_move), beta);
             }
             if score > alpha {
                 alpha = score;
@@ -137,30 +453,49 @@ impl AIState {
             }
         }
 
-        if let Some(best_so_far) = best_so_far {
-            self.update_cache(board.to_string(), best_so_far, alpha, player, depth);
-        }
+        let bound = if best_so_far.is_some() {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.transposition_table
+            .store(hash, best_so_far, alpha, ply, depth, bound);
         (best_so_far, alpha)
     }
+}
 
-    fn update_cache(
-        &mut self,
-        board_string: String,
-        chess_move: ChessMove,
-        score: ScoreType,
-        player: Color,
-        eval_depth: u8,
-    ) {
-        let new_cache_entry = CacheEntry {
-            score,
-            color: player,
-            eval_depth,
-            chess_move,
-        };
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The moves `state` plays against itself from the start
+    fn play(mut state: AIState, plies: usize) -> Vec<ChessMove> {
+        let stop = AtomicBool::new(false);
+        let mut board = Board::default();
+        let mut moves = Vec::new();
+        for _ in 0..plies {
+            let chess_move = state
+                .search(board, SearchLimits::infinite(), &stop, |_| {})
+                .unwrap();
+            board = board.make_move_new(chess_move);
+            moves.push(chess_move);
+        }
+        moves
+    }
+
+    fn seeded(difficulty: Difficulty, seed: u64) -> AIState {
+        let mut state = AIState::with_hash_size(1);
+        state.set_difficulty(difficulty);
+        state.seed(seed);
+        state
+    }
 
-        self.score_cache
-            .entry(board_string)
-            .and_modify(|entry| entry.update_if_better(new_cache_entry.clone()))
-            .or_insert(new_cache_entry);
+    // Beginner searches to depth 1 only, which finishes long before its time limit,
+    // so every difference would come from the noise and the blunders
+    #[test]
+    fn same_seed_same_moves() {
+        let first = play(seeded(Difficulty::Beginner, 7), 16);
+        let second = play(seeded(Difficulty::Beginner, 7), 16);
+        assert_eq!(first, second);
     }
 }
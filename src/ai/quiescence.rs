@@ -0,0 +1,144 @@
+use super::{
+    eval::{evaluate, piece_value},
+    limits::SearchControl,
+    AIState, ScoreType, MATE,
+};
+
+use chess::{Board, ChessMove, MoveGen, Piece, EMPTY};
+
+use std::cmp::Reverse;
+
+/// How many plies into the quiescence search quiet checking moves are still tried
+pub(super) const CHECK_PLIES: u8 = 1;
+
+/// Slack given to a capture before delta pruning discards it,
+/// covering positional gains the material count does not see
+const DELTA_MARGIN: ScoreType = 2 * piece_value(Piece::Pawn);
+
+impl AIState {
+    /// Keeps searching captures and promotions (and checks close to the leaf)
+    /// until the position is quiet, so that the static evaluation is not taken
+    /// in the middle of an exchange.
+    pub(super) fn quiescence(
+        &mut self,
+        board: Board,
+        ply: u8,
+        checks_left: u8,
+        mut alpha: ScoreType,
+        beta: ScoreType,
+        control: &mut SearchControl,
+    ) -> ScoreType {
+        if control.visit_node() {
+            return 0;
+        }
+
+        let in_check = *board.checkers() != EMPTY;
+        let legal_moves = MoveGen::new_legal(&board);
+
+        if in_check && legal_moves.len() == 0 {
+            return -MATE + ScoreType::from(ply);
+        }
+
+        let stand_pat = evaluate(&board, board.side_to_move()) + self.noise(&board);
+        if !in_check {
+            // Not capturing anything is always an option, so the side to move
+            // can do at least as well as the static evaluation
+            if stand_pat >= beta {
+                return beta;
+            }
+
+            // Even winning a queen would not be enough
+            if stand_pat + piece_value(Piece::Queen) + DELTA_MARGIN < alpha {
+                return alpha;
+            }
+
+            alpha = alpha.max(stand_pat);
+        }
+
+        // When in check, every evasion has to be considered
+        let mut moves: Vec<_> = legal_moves
+            .filter(|&chess_move| {
+                in_check
+                    || captured_piece(&board, chess_move).is_some()
+                    || chess_move.get_promotion().is_some()
+                    || (checks_left > 0 && *board.make_move_new(chess_move).checkers() != EMPTY)
+            })
+            .collect();
+
+        // Most valuable victim first, then least valuable attacker
+        moves.sort_by_cached_key(|&chess_move| {
+            let victim = captured_piece(&board, chess_move).map_or(0, piece_value);
+            let attacker = board
+                .piece_on(chess_move.get_source())
+                .map_or(0, piece_value);
+            (Reverse(victim), attacker)
+        });
+
+        for chess_move in moves {
+            if !in_check && chess_move.get_promotion().is_none() {
+                if let Some(victim) = captured_piece(&board, chess_move) {
+                    if stand_pat + piece_value(victim) + DELTA_MARGIN < alpha {
+                        continue;
+                    }
+                }
+            }
+
+            let score = -self.quiescence(
+                board.make_move_new(chess_move),
+                ply + 1,
+                checks_left.saturating_sub(1),
+                -beta,
+                -alpha,
+                control,
+            );
+            if control.is_aborted() {
+                return 0;
+            }
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+
+        alpha
+    }
+}
+
+/// The piece taken by the move, if it is a capture
+fn captured_piece(board: &Board, chess_move: ChessMove) -> Option<Piece> {
+    let dest = chess_move.get_dest();
+    board.piece_on(dest).or_else(|| {
+        // A pawn moving diagonally onto an empty square is capturing en passant
+        let is_pawn = board.piece_on(chess_move.get_source()) == Some(Piece::Pawn);
+        let changes_file = chess_move.get_source().get_file() != dest.get_file();
+        (is_pawn && changes_file).then_some(Piece::Pawn)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{SearchLimits, INFINITY, MATE_THRESHOLD};
+
+    use std::{str::FromStr, sync::atomic::AtomicBool};
+
+    /// White mates with the quiet Ra8, which only a search of checks can see
+    const BACK_RANK_MATE: &str = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+
+    fn quiescence_score(checks_left: u8) -> ScoreType {
+        let board = Board::from_str(BACK_RANK_MATE).unwrap();
+        let stop = AtomicBool::new(false);
+        let mut control = SearchControl::new(SearchLimits::infinite(), &stop);
+        AIState::default().quiescence(board, 1, checks_left, -INFINITY, INFINITY, &mut control)
+    }
+
+    #[test]
+    fn quiet_checks_are_searched_near_the_leaf() {
+        assert!(quiescence_score(CHECK_PLIES) > MATE_THRESHOLD);
+    }
+
+    #[test]
+    fn quiet_checks_stop_once_none_are_left() {
+        assert!(quiescence_score(0) < MATE_THRESHOLD);
+    }
+}
@@ -0,0 +1,280 @@
+//! Syzygy endgame tablebases, which know the result of every position with few enough pieces.
+//! The tables are read with `shakmaty-syzygy` when the `syzygy` feature is enabled.
+
+use chess::{Board, CastleRights, ChessMove, ALL_COLORS};
+#[cfg(feature = "syzygy")]
+use chess::{MoveGen, Piece};
+
+use std::path::Path;
+
+use super::{ScoreType, TB_WIN};
+
+/// The result of a position with best play, from the point of view of the side to move.
+/// Cursed wins and blessed losses would be wins and losses if not for the fifty-move rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    /// The search score of the result, `ply` moves from the root.
+    /// Wins found sooner score higher, like mates, but always below any mate.
+    pub fn score(self, ply: u8) -> ScoreType {
+        match self {
+            Wdl::Win => TB_WIN - ScoreType::from(ply),
+            Wdl::Loss => -TB_WIN + ScoreType::from(ply),
+            Wdl::CursedWin | Wdl::Draw | Wdl::BlessedLoss => 0,
+        }
+    }
+}
+
+/// The Syzygy tables found in a directory
+pub struct Tablebase {
+    #[cfg(feature = "syzygy")]
+    tables: shakmaty_syzygy::Tablebase<shakmaty::Chess>,
+    /// The number of table files found
+    len: usize,
+}
+
+impl Tablebase {
+    /// The number of table files found
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether `board` has few enough pieces to be looked up, and no castling rights.
+    /// It can still be missing if the directory lacks the table for its material.
+    pub fn covers(&self, board: &Board) -> bool {
+        board.combined().popcnt() <= self.max_pieces()
+            && ALL_COLORS
+                .iter()
+                .all(|&color| board.castle_rights(color) == CastleRights::NoRights)
+    }
+}
+
+#[cfg(feature = "syzygy")]
+impl Tablebase {
+    /// Finds the tables in `directory`. Other files are skipped,
+    /// so a directory without any tables gives an empty tablebase rather than an error.
+    pub fn open<P: AsRef<Path>>(directory: P) -> Result<Self, String> {
+        let directory = directory.as_ref();
+        let mut tables = shakmaty_syzygy::Tablebase::new();
+        let len = tables
+            .add_directory(directory)
+            .map_err(|e| format!("{}: {}", directory.display(), e))?;
+        Ok(Self { tables, len })
+    }
+
+    /// The most pieces a position can have, kings included, to be in the tables
+    pub fn max_pieces(&self) -> u32 {
+        self.tables.max_pieces() as u32
+    }
+
+    /// The result of `board` with best play for the side to move, or `None` if it is not in the tables.
+    /// The board does not know how long ago the last capture or pawn move was,
+    /// so the result is the one right after such a move.
+    pub fn probe_wdl(&self, board: &Board) -> Option<Wdl> {
+        if !self.covers(board) {
+            return None;
+        }
+        let position = to_position(board)?;
+        let wdl = self.tables.probe_wdl_after_zeroing(&position).ok()?;
+        Some(match wdl {
+            shakmaty_syzygy::Wdl::Loss => Wdl::Loss,
+            shakmaty_syzygy::Wdl::BlessedLoss => Wdl::BlessedLoss,
+            shakmaty_syzygy::Wdl::Draw => Wdl::Draw,
+            shakmaty_syzygy::Wdl::CursedWin => Wdl::CursedWin,
+            shakmaty_syzygy::Wdl::Win => Wdl::Win,
+        })
+    }
+
+    /// The move that keeps the best result of `board` while going for a capture or pawn move
+    /// the fastest when winning (the slowest when losing), along with that result.
+    /// `None` if the position or any position after it is missing from the tables.
+    pub fn best_move(&self, board: &Board) -> Option<(ChessMove, Wdl)> {
+        let wdl = self.probe_wdl(board)?;
+        let position = to_position(board)?;
+        let (best, _) = self.tables.best_move(&position).ok()??;
+
+        let source = best.from().map(|square| square as usize);
+        let dest = best.to() as usize;
+        let promotion = best.promotion().map(|role| match role {
+            shakmaty::Role::Knight => Piece::Knight,
+            shakmaty::Role::Bishop => Piece::Bishop,
+            shakmaty::Role::Rook => Piece::Rook,
+            _ => Piece::Queen,
+        });
+        let chess_move = MoveGen::new_legal(board).find(|chess_move| {
+            Some(chess_move.get_source().to_index()) == source
+                && chess_move.get_dest().to_index() == dest
+                && chess_move.get_promotion() == promotion
+        })?;
+        Some((chess_move, wdl))
+    }
+}
+
+#[cfg(not(feature = "syzygy"))]
+impl Tablebase {
+    /// Always fails, since this build cannot read the tables
+    pub fn open<P: AsRef<Path>>(directory: P) -> Result<Self, String> {
+        Err(format!(
+            "{}: built without the syzygy feature, so tablebases cannot be read",
+            directory.as_ref().display()
+        ))
+    }
+
+    pub fn max_pieces(&self) -> u32 {
+        0
+    }
+
+    pub fn probe_wdl(&self, _board: &Board) -> Option<Wdl> {
+        None
+    }
+
+    pub fn best_move(&self, _board: &Board) -> Option<(ChessMove, Wdl)> {
+        None
+    }
+}
+
+/// `board` as a position `shakmaty` can look up, or `None` if it does not accept it
+#[cfg(feature = "syzygy")]
+fn to_position(board: &Board) -> Option<shakmaty::Chess> {
+    use chess::Color;
+    use shakmaty::{Bitboard, ByColor, ByRole, CastlingMode, Chess, FromSetup, Setup};
+
+    let pieces = |piece: Piece| Bitboard(board.pieces(piece).0);
+    let colors = |color: Color| Bitboard(board.color_combined(color).0);
+    let mut setup = Setup::empty();
+    setup.board = shakmaty::Board::from_bitboards(
+        ByRole {
+            pawn: pieces(Piece::Pawn),
+            knight: pieces(Piece::Knight),
+            bishop: pieces(Piece::Bishop),
+            rook: pieces(Piece::Rook),
+            queen: pieces(Piece::Queen),
+            king: pieces(Piece::King),
+        },
+        ByColor {
+            black: colors(Color::Black),
+            white: colors(Color::White),
+        },
+    );
+    setup.turn = match board.side_to_move() {
+        Color::White => shakmaty::Color::White,
+        Color::Black => shakmaty::Color::Black,
+    };
+    // The board keeps the pawn that can be taken; the setup wants the square behind it
+    setup.ep_square = board.en_passant().map(|square| {
+        let behind = match board.side_to_move() {
+            Color::White => square.up(),
+            Color::Black => square.down(),
+        };
+        shakmaty::Square::new(behind.unwrap().to_index() as u32)
+    });
+    Chess::from_setup(setup, CastlingMode::Standard).ok()
+}
+
+#[cfg(all(test, feature = "syzygy"))]
+mod tests {
+    use super::*;
+    use crate::ai::{AIState, SearchInfo, SearchLimits};
+
+    use std::{str::FromStr, sync::atomic::AtomicBool};
+
+    /// Holds the KQvK and KRvK tables
+    const TABLES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy");
+
+    fn probe(fen: &str) -> Option<Wdl> {
+        let tablebase = Tablebase::open(TABLES).unwrap();
+        tablebase.probe_wdl(&Board::from_str(fen).unwrap())
+    }
+
+    #[test]
+    fn finds_the_tables() {
+        let tablebase = Tablebase::open(TABLES).unwrap();
+        assert_eq!(tablebase.len(), 4);
+        assert_eq!(tablebase.max_pieces(), 3);
+    }
+
+    #[test]
+    fn a_directory_without_tables_is_empty() {
+        let tablebase = Tablebase::open(env!("CARGO_MANIFEST_DIR")).unwrap();
+        assert!(tablebase.is_empty());
+        assert_eq!(
+            tablebase.probe_wdl(&Board::from_str("4k3/8/8/8/8/8/8/4K2Q w - - 0 1").unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn queen_and_rook_win() {
+        assert_eq!(probe("4k3/8/8/8/8/8/8/4K2Q w - - 0 1"), Some(Wdl::Win));
+        assert_eq!(probe("4k3/8/8/8/8/8/8/4K2Q b - - 0 1"), Some(Wdl::Loss));
+        assert_eq!(probe("4k3/8/8/8/8/8/8/R3K3 b - - 0 1"), Some(Wdl::Loss));
+    }
+
+    #[test]
+    fn a_hanging_queen_is_a_draw() {
+        // Black takes the queen, which no white piece defends
+        assert_eq!(probe("8/8/8/8/8/8/6kQ/4K3 b - - 0 1"), Some(Wdl::Draw));
+    }
+
+    #[test]
+    fn positions_without_a_table_are_not_found() {
+        assert_eq!(probe("4k3/8/8/8/8/8/8/3QK2R w - - 0 1"), None);
+        assert_eq!(probe("4k3/8/8/8/8/8/8/3QK3 w - - 0 1"), Some(Wdl::Win));
+        assert_eq!(probe("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1"), None);
+    }
+
+    #[test]
+    fn best_move_mates() {
+        let tablebase = Tablebase::open(TABLES).unwrap();
+        let board = Board::from_str("k7/8/1K6/8/8/8/8/7R w - - 0 1").unwrap();
+        let (chess_move, wdl) = tablebase.best_move(&board).unwrap();
+        assert_eq!(chess_move.to_string(), "h1h8");
+        assert_eq!(wdl, Wdl::Win);
+    }
+
+    fn search(fen: &str) -> (ChessMove, ScoreType) {
+        let mut state = AIState::default();
+        state.set_tablebase(Some(std::sync::Arc::new(Tablebase::open(TABLES).unwrap())));
+        let stop = AtomicBool::new(false);
+        let mut score = 0;
+        let limits = SearchLimits {
+            depth: Some(2),
+            ..SearchLimits::default()
+        };
+        let chess_move = state
+            .search(
+                Board::from_str(fen).unwrap(),
+                limits,
+                &stop,
+                |info: &SearchInfo| score = info.score,
+            )
+            .unwrap();
+        (chess_move, score)
+    }
+
+    #[test]
+    fn the_root_plays_the_tablebase_move() {
+        let (chess_move, score) = search("k7/8/1K6/8/8/8/8/7R w - - 0 1");
+        assert_eq!(chess_move.to_string(), "h1h8");
+        assert_eq!(score, TB_WIN);
+    }
+
+    #[test]
+    fn the_search_stops_at_positions_in_the_tables() {
+        // KQvKN is not in the tables, but taking the knight reaches KQvK
+        let (chess_move, score) = search("4k3/8/8/3n4/8/8/8/3QK3 w - - 0 1");
+        assert_eq!(chess_move.to_string(), "d1d5");
+        assert_eq!(score, Wdl::Win.score(1));
+    }
+}
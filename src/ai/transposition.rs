@@ -0,0 +1,270 @@
+use super::{ScoreType, MATE_THRESHOLD};
+
+use chess::ChessMove;
+
+/// Size of a table created with `Default`
+pub const DEFAULT_MEGABYTES: usize = 24;
+
+/// How a stored score relates to the true score of the position
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum Bound {
+    /// The score is exact
+    Exact,
+    /// The search failed high, so the true score is at least this
+    Lower,
+    /// The search failed low, so the true score is at most this
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Entry {
+    hash: u64,
+    pub chess_move: Option<ChessMove>,
+    /// Mate scores are stored relative to this node rather than the root
+    score: ScoreType,
+    pub depth: u8,
+    pub bound: Bound,
+    age: u8,
+}
+
+impl Entry {
+    /// The stored score, as seen from a node `ply` moves away from the root
+    pub fn score(&self, ply: u8) -> ScoreType {
+        let ply = ScoreType::from(ply);
+        if self.score > MATE_THRESHOLD {
+            self.score - ply
+        } else if self.score < -MATE_THRESHOLD {
+            self.score + ply
+        } else {
+            self.score
+        }
+    }
+}
+
+/// A fixed-size hash table of search results keyed on the Zobrist hash of the board
+pub(super) struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+    /// Incremented every search so that stale entries get replaced first
+    age: u8,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::with_megabytes(DEFAULT_MEGABYTES)
+    }
+}
+
+impl TranspositionTable {
+    /// Creates a table with at least `size` entries (rounded up to a power of two)
+    pub fn new(size: usize) -> Self {
+        Self {
+            entries: vec![None; size.max(1).next_power_of_two()],
+            age: 0,
+        }
+    }
+
+    /// Creates a table that uses about `megabytes` of memory
+    pub fn with_megabytes(megabytes: usize) -> Self {
+        let entry_size = std::mem::size_of::<Option<Entry>>();
+        // Rounding up to a power of two could double the size, so round down instead
+        let entries = (megabytes * 1024 * 1024 / entry_size).max(1);
+        Self::new((entries / 2 + 1).next_power_of_two())
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.iter_mut().for_each(|entry| *entry = None);
+        self.age = 0;
+    }
+
+    /// Marks every existing entry as coming from an older search
+    pub fn new_search(&mut self) {
+        self.age = self.age.wrapping_add(1);
+    }
+
+    pub fn get(&self, hash: u64) -> Option<&Entry> {
+        self.entries[self.index(hash)]
+            .as_ref()
+            .filter(|entry| entry.hash == hash)
+    }
+
+    pub fn store(
+        &mut self,
+        hash: u64,
+        chess_move: Option<ChessMove>,
+        score: ScoreType,
+        ply: u8,
+        depth: u8,
+        bound: Bound,
+    ) {
+        // Mates are stored as a distance from this node, so they stay correct
+        // when the position is reached again at a different ply
+        let score = if score > MATE_THRESHOLD {
+            score + ScoreType::from(ply)
+        } else if score < -MATE_THRESHOLD {
+            score - ScoreType::from(ply)
+        } else {
+            score
+        };
+
+        let index = self.index(hash);
+        let age = self.age;
+        let slot = &mut self.entries[index];
+
+        let should_replace = match slot {
+            None => true,
+            // Prefer results from the current search, then deeper results
+            Some(existing) => {
+                existing.hash == hash || existing.age != age || existing.depth <= depth
+            }
+        };
+
+        if should_replace {
+            // Keep the old best move if the new search did not find one
+            let chess_move = chess_move.or_else(|| {
+                slot.filter(|existing| existing.hash == hash)
+                    .and_then(|existing| existing.chess_move)
+            });
+
+            *slot = Some(Entry {
+                hash,
+                chess_move,
+                score,
+                depth,
+                bound,
+                age,
+            });
+        }
+    }
+
+    /// How much of the table is used by the current search, in permille
+    pub fn hashfull(&self) -> usize {
+        let sample = self.entries.len().min(1000);
+        self.entries[..sample]
+            .iter()
+            .filter(|entry| matches!(entry, Some(entry) if entry.age == self.age))
+            .count()
+            * 1000
+            / sample
+    }
+
+    fn index(&self, hash: u64) -> usize {
+        // The length is a power of two, so masking is a cheap modulo
+        (hash as usize) & (self.entries.len() - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::MATE;
+
+    use chess::Square;
+
+    fn some_move() -> Option<ChessMove> {
+        Some(ChessMove::new(Square::E2, Square::E4, None))
+    }
+
+    #[test]
+    fn stores_and_gets() {
+        let mut table = TranspositionTable::new(16);
+        assert!(table.get(42).is_none());
+
+        table.store(42, some_move(), 35, 3, 5, Bound::Exact);
+        let entry = table.get(42).unwrap();
+        assert_eq!(entry.chess_move, some_move());
+        assert_eq!(entry.score(3), 35);
+        assert_eq!(entry.depth, 5);
+
+        // Same index, different position
+        assert!(table.get(42 + 16).is_none());
+
+        table.clear();
+        assert!(table.get(42).is_none());
+    }
+
+    #[test]
+    fn keeps_the_bound() {
+        let mut table = TranspositionTable::new(16);
+        for (hash, &bound) in [Bound::Exact, Bound::Lower, Bound::Upper]
+            .iter()
+            .enumerate()
+        {
+            table.store(hash as u64, None, 10, 0, 1, bound);
+            assert_eq!(table.get(hash as u64).unwrap().bound, bound);
+        }
+    }
+
+    #[test]
+    fn keeps_the_old_move_without_a_new_one() {
+        let mut table = TranspositionTable::new(16);
+        table.store(7, some_move(), 10, 0, 1, Bound::Lower);
+        table.store(7, None, -5, 0, 2, Bound::Upper);
+        let entry = table.get(7).unwrap();
+        assert_eq!(entry.chess_move, some_move());
+        assert_eq!(entry.score(0), -5);
+        assert_eq!(entry.bound, Bound::Upper);
+    }
+
+    #[test]
+    fn mate_scores_are_relative_to_the_node() {
+        let mut table = TranspositionTable::new(16);
+        // Mate in 3 plies from the root, found at a node 2 plies deep: mate in 1 from there
+        table.store(1, None, MATE - 3, 2, 4, Bound::Exact);
+        assert_eq!(table.get(1).unwrap().score, MATE - 1);
+        // Reached again 4 plies deep, it is mate 5 plies from the root
+        assert_eq!(table.get(1).unwrap().score(4), MATE - 5);
+        assert_eq!(table.get(1).unwrap().score(2), MATE - 3);
+
+        table.store(2, None, -MATE + 6, 4, 4, Bound::Exact);
+        assert_eq!(table.get(2).unwrap().score, -MATE + 2);
+        assert_eq!(table.get(2).unwrap().score(1), -MATE + 3);
+
+        // Ordinary scores do not depend on the ply
+        table.store(3, None, 250, 4, 4, Bound::Exact);
+        assert_eq!(table.get(3).unwrap().score(9), 250);
+    }
+
+    #[test]
+    fn deeper_results_replace_shallower_ones() {
+        let mut table = TranspositionTable::new(16);
+        // 5 and 21 share a slot
+        table.store(5, None, 1, 0, 3, Bound::Exact);
+        table.store(21, None, 2, 0, 2, Bound::Exact);
+        assert!(table.get(5).is_some());
+        assert!(table.get(21).is_none());
+
+        table.store(21, None, 2, 0, 3, Bound::Exact);
+        assert!(table.get(5).is_none());
+        assert!(table.get(21).is_some());
+    }
+
+    #[test]
+    fn results_of_older_searches_are_replaced_first() {
+        let mut table = TranspositionTable::new(16);
+        table.store(5, None, 1, 0, 8, Bound::Exact);
+        table.new_search();
+        table.store(21, None, 2, 0, 1, Bound::Exact);
+        assert!(table.get(5).is_none());
+        assert_eq!(table.get(21).unwrap().depth, 1);
+    }
+
+    #[test]
+    fn the_same_position_is_always_replaced() {
+        let mut table = TranspositionTable::new(16);
+        table.store(5, None, 1, 0, 8, Bound::Exact);
+        table.store(5, None, 2, 0, 1, Bound::Lower);
+        assert_eq!(table.get(5).unwrap().depth, 1);
+        assert_eq!(table.get(5).unwrap().score(0), 2);
+    }
+
+    #[test]
+    fn hashfull_counts_the_current_search() {
+        let mut table = TranspositionTable::new(4);
+        assert_eq!(table.hashfull(), 0);
+        table.store(0, None, 0, 0, 1, Bound::Exact);
+        table.store(1, None, 0, 0, 1, Bound::Exact);
+        assert_eq!(table.hashfull(), 500);
+        table.new_search();
+        assert_eq!(table.hashfull(), 0);
+    }
+}
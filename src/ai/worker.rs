@@ -0,0 +1,87 @@
+use super::{AIState, Progress, SearchLimits};
+
+use chess::{Board, ChessMove};
+
+use std::{
+    panic,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+/// An AI search running on a background thread.
+/// The thread owns the AI's state while it searches, so nothing has to wait for a lock;
+/// `stop` hands the state back.
+/// Dropping the worker cancels the search and waits for the thread to finish.
+pub struct AIWorker {
+    stop: Arc<AtomicBool>,
+    result: Receiver<ChessMove>,
+    progress: Receiver<Progress>,
+    handle: Option<JoinHandle<AIState>>,
+}
+
+impl AIWorker {
+    /// Starts searching for a move on `board`, which is `ply` moves into the game
+    pub fn spawn(mut ai_state: AIState, board: Board, ply: usize, limits: SearchLimits) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, result) = mpsc::channel();
+        let (progress_sender, progress) = mpsc::channel();
+
+        let handle = {
+            let stop = stop.clone();
+            thread::spawn(move || {
+                let chess_move = ai_state.best_move(board, ply, limits, &stop, |update| {
+                    // Like the result, nobody may be listening any more
+                    let _ = progress_sender.send(update);
+                });
+                if let Some(chess_move) = chess_move.filter(|_| !stop.load(Ordering::Relaxed)) {
+                    // The receiver is gone if the worker was dropped, which is fine
+                    let _ = sender.send(chess_move);
+                }
+                ai_state
+            })
+        };
+
+        Self {
+            stop,
+            result,
+            progress,
+            handle: Some(handle),
+        }
+    }
+
+    /// Returns the move found by the search, if it has finished
+    pub fn poll(&self) -> Option<ChessMove> {
+        self.result.try_recv().ok()
+    }
+
+    /// What the search has reported since the last call
+    pub fn progress(&self) -> impl Iterator<Item = Progress> + '_ {
+        self.progress.try_iter()
+    }
+
+    /// Stops the search if it is still running, waits for the thread to exit
+    /// and hands back the state along with what the search added to it
+    pub fn stop(mut self) -> AIState {
+        self.stop.store(true, Ordering::Relaxed);
+        let handle = self.handle.take().expect("Only `stop` takes the handle");
+        // The state is gone with the thread, as it would be with a poisoned lock
+        handle
+            .join()
+            .unwrap_or_else(|panic| panic::resume_unwind(panic))
+    }
+}
+
+impl Drop for AIWorker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                eprintln!("AI thread panicked");
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Builds an opening book in the Polyglot format from lines of moves,
+//! such as the one bundled in `assets/`
+
+use std::{convert::TryFrom, fs, path::PathBuf, process};
+
+use sdl_game::ai::book::OpeningBook;
+
+const USAGE: &str = "Usage: makebook [--plies <n>] <lines> <book>
+
+Reads lines of moves in SAN from the initial position, e.g. 1. e4 e5 2. Nf3 Nc6,
+and writes a book where each move is weighted by how many lines play it.
+
+Options:
+    --plies <n>  How many moves of each line go into the book [default: 24]";
+
+/// Lines are cut off here unless `--plies` says otherwise
+const DEFAULT_PLIES: usize = 24;
+
+struct Options {
+    lines: PathBuf,
+    book: PathBuf,
+    plies: usize,
+}
+
+fn parse_options<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut plies = DEFAULT_PLIES;
+    let mut paths = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--plies" => {
+                let count = args
+                    .next()
+                    .ok_or_else(|| format!("Missing value for {}", arg))?;
+                plies = count
+                    .parse()
+                    .map_err(|e| format!("Invalid number of plies {}: {}", count, e))?;
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown argument {}", arg)),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    match <[PathBuf; 2]>::try_from(paths) {
+        Ok([lines, book]) => Ok(Options { lines, book, plies }),
+        Err(_) => Err("Expected a file of lines and a book to write".to_string()),
+    }
+}
+
+fn main() {
+    let options = parse_options(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}\n{}", e, USAGE);
+        process::exit(2);
+    });
+
+    let book = fs::read_to_string(&options.lines)
+        .map_err(|e| e.to_string())
+        .and_then(|text| OpeningBook::from_lines(&text, options.plies))
+        .unwrap_or_else(|e| {
+            eprintln!("Could not read {}: {}", options.lines.display(), e);
+            process::exit(1);
+        });
+
+    if let Err(e) = fs::write(&options.book, book.to_bytes()) {
+        eprintln!("Could not write {}: {}", options.book.display(), e);
+        process::exit(1);
+    }
+    println!("Wrote {} moves to {}", book.len(), options.book.display());
+}
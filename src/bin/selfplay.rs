@@ -0,0 +1,106 @@
+//! Plays a match between two engine configurations without any window
+//! and reports how the first one scored against the second
+
+use std::{convert::TryFrom, fs, path::PathBuf, process};
+
+use sdl_game::selfplay::{self, EngineConfig, Match};
+
+const USAGE: &str =
+    "Usage: selfplay [--games <n>] [--openings <file>] [--pgn <file>] <engine> <engine>
+
+Engines are comma-separated settings, e.g. name=new,hash=64,movetime=100.
+The settings are name, hash (megabytes), depth, nodes, movetime (milliseconds),
+difficulty (beginner, easy, medium, hard or full) and seed (for repeatable games);
+an engine without depth, nodes or movetime thinks for 100 milliseconds per move.
+
+Options:
+    --games <n>        How many games to play [default: 2]
+    --openings <file>  Start positions, one FEN per line, each played with both colors
+                       [default: the initial position]
+    --pgn <file>       Where to write every game [default: selfplay.pgn]";
+
+struct Options {
+    chess_match: Match,
+    pgn: PathBuf,
+}
+
+fn parse_options<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut games = 2;
+    let mut openings = Vec::new();
+    let mut pgn = PathBuf::from("selfplay.pgn");
+    let mut engines = Vec::new();
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("Missing value for {}", arg))
+        };
+        match arg.as_str() {
+            "--games" => {
+                let count = value()?;
+                games = count
+                    .parse()
+                    .map_err(|e| format!("Invalid game count {}: {}", count, e))?;
+            }
+            "--openings" => {
+                let path = value()?;
+                let text = fs::read_to_string(&path)
+                    .map_err(|e| format!("Could not read {}: {}", path, e))?;
+                openings =
+                    selfplay::parse_openings(&text).map_err(|e| format!("{}: {}", path, e))?;
+            }
+            "--pgn" => pgn = value()?.into(),
+            _ if arg.starts_with("--") => return Err(format!("Unknown argument {}", arg)),
+            _ => engines.push(arg.parse::<EngineConfig>()?),
+        }
+    }
+
+    let engines = <[EngineConfig; 2]>::try_from(engines)
+        .map_err(|engines| format!("Expected two engines, found {}", engines.len()))?;
+
+    Ok(Options {
+        chess_match: Match {
+            engines,
+            openings,
+            games,
+        },
+        pgn,
+    })
+}
+
+fn main() {
+    let options = parse_options(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}\n{}", e, USAGE);
+        process::exit(2);
+    });
+    let [first, second] = &options.chess_match.engines;
+    println!("{} vs {}", first.name, second.name);
+
+    let mut pgns = Vec::new();
+    let stats = options.chess_match.play(|round, record, stats| {
+        println!(
+            "Game {}: {} - {} {} ({}); {} {}",
+            round,
+            record.pgn.tag("White").unwrap_or("?"),
+            record.pgn.tag("Black").unwrap_or("?"),
+            record.pgn.result,
+            record.outcome,
+            first.name,
+            stats
+        );
+        pgns.push(record.pgn.to_string());
+    });
+
+    println!(
+        "Final score of {} vs {}: {}",
+        first.name, second.name, stats
+    );
+
+    match fs::write(&options.pgn, pgns.join("\n")) {
+        Ok(()) => println!("Saved {} games to {}", pgns.len(), options.pgn.display()),
+        Err(e) => {
+            eprintln!("Could not save games to {}: {}", options.pgn.display(), e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,5 @@
+//! Runs the engine as a UCI engine on stdin/stdout, without any window
+
+fn main() {
+    sdl_game::uci::run();
+}
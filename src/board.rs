@@ -1,31 +1,102 @@
-use chess::{Board, BoardStatus, ChessMove, File, MoveGen, Rank, Square, NUM_FILES};
+use chess::{Board, ChessMove, File, Piece, Rank, Square, EMPTY, NUM_FILES};
 
 use sdl2::{
     pixels::Color,
     rect::{Point, Rect},
+    render::BlendMode,
 };
 
-use std::convert::{TryFrom, TryInto};
+use std::{
+    convert::{TryFrom, TryInto},
+    rc::Rc,
+    time::Duration,
+};
+
+use sdl_game::{
+    clock,
+    game::{ChessGame, Outcome},
+};
 
 use crate::{
-    ai::AIState,
     drawable::{Drawable, Renderer},
+    move_list::{MoveList, MoveListView},
     sprite::Sprite,
+    text::{Font, Label},
+    theme::{Theme, ThemeSprites},
     utils,
 };
 
+/// The buttons on the banner shown when the game is over
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameOverChoice {
+    NewGame,
+    Quit,
+}
+
+/// A piece picked up with the mouse
+struct Drag {
+    from: Square,
+    /// Where the cursor is, relative to the center of the board
+    cursor: Point,
+}
+
+/// Draws a `ChessGame` and maps clicks onto its squares
 pub struct ChessBoard<'a> {
-    board: Board,
-    sprites: Vec<Sprite<'a>>,
-    selected_square: Option<Square>,
-    ai_state: AIState,
-    player_side: chess::Color,
+    game: ChessGame,
+    sprites: ThemeSprites<'a>,
+    font: Rc<Font<'a>>,
+    theme: Theme,
+    move_list: MoveList,
+    /// The number of moves played in the position being reviewed,
+    /// or `None` while the board shows the game as it is
+    review: Option<usize>,
+    drag: Option<Drag>,
+    /// Whether Black is drawn at the bottom
+    flipped: bool,
+    /// The size of a square in pixels, set by `fit` to suit the window
+    tile_size: u32,
 }
 
-const TILE_SIZE: u8 = 32;
+/// The smallest a square is drawn at, and the size of the sprites shown along the top of the window
+const MIN_TILE_SIZE: u32 = 32;
+
+/// Clocks with less than this left are drawn in the theme's low time color
+const LOW_TIME: Duration = Duration::from_secs(10);
+
+/// Width of the clock bars and their gap to the board, in pixels
+const CLOCK_WIDTH: u32 = 12;
+const CLOCK_MARGIN: i32 = 8;
+
+/// Gap between the board and the text around it, in pixels
+const TEXT_MARGIN: i32 = 12;
+
+/// Gap between the board and the move list, leaving room for the clocks, and its width
+const MOVE_LIST_OFFSET: i32 = 90;
+const MOVE_LIST_WIDTH: u32 = 170;
+
+/// Space kept free at the top and bottom of the window for the sprite row and the text
+/// around the board, and at either side for the clocks and move list, in pixels
+const VERTICAL_MARGIN: i32 = MIN_TILE_SIZE as i32 + 2 * TEXT_MARGIN;
+const HORIZONTAL_MARGIN: i32 = MOVE_LIST_OFFSET + MOVE_LIST_WIDTH as i32 + TEXT_MARGIN;
+
+/// Size of the markers on the squares the selected piece can move to, as a fraction of a tile
+const MOVE_DOT_RADIUS: f64 = 0.15;
+const CAPTURE_RING_WIDTH: f64 = 0.1;
+
+/// How many layers the glow around a king in check has, and how far each is inset, in pixels
+const CHECK_GLOW_LAYERS: i32 = 3;
+const CHECK_GLOW_STEP: i32 = 4;
+
+/// The pieces offered when promoting, in the order they are shown from the last rank inwards
+const PROMOTION_PIECES: [Piece; 4] = [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight];
 
 impl<'a> ChessBoard<'a> {
-    pub fn new(sprites: Vec<Sprite<'a>>) -> ChessBoard {
+    pub fn new(
+        sprites: ThemeSprites<'a>,
+        font: Rc<Font<'a>>,
+        theme: Theme,
+        game: ChessGame,
+    ) -> ChessBoard<'a> {
         chess::ALL_SQUARES
             .iter()
             .map(|&square| (square, chess::BoardBuilder::from(Board::default())[square]))
@@ -40,34 +111,209 @@ impl<'a> ChessBoard<'a> {
                 )
             });
 
-        let board = Default::default();
+        let flipped = game.mode().bottom_side() == chess::Color::Black;
         Self {
+            game,
             sprites,
-            ai_state: AIState::default(),
-            board,
-            selected_square: Default::default(),
-            player_side: chess::Color::White,
+            font,
+            theme,
+            move_list: MoveList::default(),
+            review: None,
+            drag: None,
+            tile_size: MIN_TILE_SIZE,
+            flipped,
+        }
+    }
+
+    pub fn game(&self) -> &ChessGame {
+        &self.game
+    }
+
+    pub fn game_mut(&mut self) -> &mut ChessGame {
+        &mut self.game
+    }
+
+    /// Replaces the game, turning the board so the human's side is at the bottom
+    pub fn set_game(&mut self, game: ChessGame) {
+        self.flipped = game.mode().bottom_side() == chess::Color::Black;
+        self.game = game;
+        self.review = None;
+        self.drag = None;
+        self.move_list.scroll_to_end();
+    }
+
+    /// Handles the mouse button going down at the given point (relative to the center):
+    /// selects the square as a click would, and picks up the piece if it can be moved
+    pub fn press(&mut self, p: Point) {
+        let square = self.tile_coord(p);
+        self.game.select(square);
+
+        // Completing a move clears the selection, so this only picks up pieces of the side to move
+        self.drag = square
+            .filter(|&square| self.game.selected_square() == Some(square))
+            .filter(|&square| !self.game.moves_from(square).is_empty())
+            .map(|from| Drag { from, cursor: p });
+    }
+
+    /// Moves the piece being dragged, if any, to follow the cursor
+    pub fn drag_to(&mut self, p: Point) {
+        if let Some(drag) = &mut self.drag {
+            drag.cursor = p;
+        }
+    }
+
+    /// Drops the piece being dragged, making the move if it was released over a legal destination.
+    /// Otherwise it snaps back and stays selected, so it can still be moved with a second click.
+    pub fn release(&mut self, p: Point) {
+        let drag = match self.drag.take() {
+            // The selection is gone if the game changed while dragging, e.g. by undoing a move
+            Some(drag) if self.game.selected_square() == Some(drag.from) => drag,
+            _ => return,
+        };
+
+        if let Some(dest) = self.tile_coord(p).filter(|&dest| dest != drag.from) {
+            let is_legal = self
+                .game
+                .moves_from(drag.from)
+                .iter()
+                .any(|chess_move| chess_move.get_dest() == dest);
+            if is_legal {
+                self.game.select(Some(dest));
+            }
+        }
+    }
+
+    /// Whether an earlier position is shown, in which case the board can not be played on
+    pub fn is_reviewing(&self) -> bool {
+        self.reviewed_plies().is_some()
+    }
+
+    /// Goes back to showing the game as it is
+    pub fn stop_review(&mut self) {
+        self.review = None;
+    }
+
+    /// The number of moves played in the position being reviewed.
+    /// Moves that have been undone since can no longer be reviewed.
+    fn reviewed_plies(&self) -> Option<usize> {
+        self.review
+            .filter(|&plies| plies < self.game.history().len())
+    }
+
+    /// The position shown on the board
+    fn displayed_board(&self) -> &Board {
+        match self.reviewed_plies() {
+            Some(plies) => &self.game.history()[plies].before,
+            None => self.game.board(),
+        }
+    }
+
+    /// The move that led to the position shown on the board
+    fn displayed_move(&self) -> Option<ChessMove> {
+        let plies = self
+            .reviewed_plies()
+            .unwrap_or_else(|| self.game.history().len());
+        let index = plies.checked_sub(1)?;
+        Some(self.game.history()[index].chess_move)
+    }
+
+    /// Where the move list sits, to the right of the clocks
+    fn move_list_area(&self, center: Point) -> Rect {
+        let half_board_size = self.board_size() / 2;
+        let height = u32::try_from(self.board_size()).unwrap();
+        Rect::new(
+            center.x() + half_board_size + MOVE_LIST_OFFSET,
+            center.y() - half_board_size,
+            MOVE_LIST_WIDTH,
+            height,
+        )
+    }
+
+    /// Jumps to the position after the move at the given point (relative to the center)
+    /// if there is one. Jumping to the last move leaves review mode.
+    /// Returns whether the point was on the move list.
+    pub fn click_move_list(&mut self, p: Point) -> bool {
+        // Points relative to the center count up, while the list is laid out downwards
+        let on_screen = Point::new(p.x(), -p.y());
+        let area = self.move_list_area(Point::new(0, 0));
+        if !area.contains_point(on_screen) {
+            return false;
+        }
+
+        if let Some(ply) = self
+            .move_list
+            .ply_at(&self.game, &self.font, area, on_screen)
+        {
+            let plies = ply + 1;
+            if plies == self.game.history().len() {
+                self.stop_review();
+            } else {
+                // The status line says which move is being reviewed
+                self.review = Some(plies);
+            }
+        }
+        true
+    }
+
+    /// Scrolls the move list by `rows`, downwards for positive values
+    pub fn scroll_moves(&mut self, rows: i32) {
+        let area = self.move_list_area(Point::new(0, 0));
+        self.move_list.scroll(rows, &self.game, &self.font, area);
+    }
+
+    /// Scales the board to the largest size at which it fits, along with everything around it,
+    /// when centered in a window of the given size
+    pub fn fit(&mut self, (width, height): (u32, u32)) {
+        let files = i32::try_from(NUM_FILES).unwrap();
+        let width = i32::try_from(width).unwrap() - 2 * HORIZONTAL_MARGIN;
+        let height = i32::try_from(height).unwrap() - 2 * VERTICAL_MARGIN;
+        let tile_size = u32::try_from(width.min(height) / files).unwrap_or(0);
+        self.tile_size = tile_size.max(MIN_TILE_SIZE);
+    }
+
+    /// Draws the board with other colors and pieces from now on
+    pub fn set_theme(&mut self, theme: Theme, sprites: ThemeSprites<'a>) {
+        self.theme = theme;
+        self.sprites = sprites;
+    }
+
+    /// Turns the board around without changing the game
+    pub fn flip(&mut self) {
+        self.flipped = !self.flipped;
+    }
+
+    /// The (column, row) of the square as drawn, counting from the bottom left
+    fn view_coords(&self, square: Square) -> (usize, usize) {
+        let (file, rank) = (square.get_file().to_index(), square.get_rank().to_index());
+        if self.flipped {
+            (NUM_FILES - 1 - file, NUM_FILES - 1 - rank)
+        } else {
+            (file, rank)
         }
     }
 
     /// Checks if the pixel position (relative to the center of the board) is inside the board
-    pub fn contains_from_center(p: Point) -> bool {
-        let board_size: u32 = Self::board_size().try_into().unwrap();
+    pub fn contains_from_center(&self, p: Point) -> bool {
+        let board_size: u32 = self.board_size().try_into().unwrap();
         Rect::from_center((0, 0), board_size, board_size).contains_point(p)
     }
 
     /// Returns the square corresponding to the given point (relative from the center)
-    pub fn tile_coord(p: Point) -> Option<Square> {
-        if !Self::contains_from_center(p) {
+    pub fn tile_coord(&self, p: Point) -> Option<Square> {
+        if !self.contains_from_center(p) {
             None
         } else {
-            let half_board_size = Self::board_size() / 2;
+            let half_board_size = self.board_size() / 2;
 
             let pixel_pos = p + (half_board_size, half_board_size).into();
-            let tile_pos = pixel_pos / TILE_SIZE.into();
+            let tile_pos = pixel_pos / i32::try_from(self.tile_size).unwrap();
 
-            let (tile_x, tile_y) =
+            let (mut tile_x, mut tile_y) =
                 utils::map_tuple(tile_pos.into(), |val| usize::try_from(val).unwrap());
+            if self.flipped {
+                tile_x = NUM_FILES - 1 - tile_x;
+                tile_y = NUM_FILES - 1 - tile_y;
+            }
 
             Some(Square::make_square(
                 Rank::from_index(tile_y),
@@ -76,160 +322,467 @@ impl<'a> ChessBoard<'a> {
         }
     }
 
-    pub fn select(&mut self, square: Option<Square>) {
-        if let (Some(original), Some(new_selection)) = (self.selected_square, square) {
-            let possible_moves = self.moves_from(original);
-            if let Some(chess_move) = possible_moves
-                .iter()
-                .find(|chess_move| chess_move.get_dest() == new_selection)
-            {
-                println!("Player is doing {}", chess_move);
-                let new_board = self.board.make_move_new(*chess_move);
-                self.board = new_board;
-                self.selected_square = None;
-
-                println!("AI is calculating move");
-                let ai_move = self.ai_state.best_move(self.board, !self.player_side);
-                println!("AI plays {}", ai_move);
-                self.board = self.board.make_move_new(ai_move);
-                return;
+    /// The piece the promotion picker shows at the given point (relative to the center), if any
+    pub fn promotion_choice(&self, p: Point) -> Option<Piece> {
+        let square = self.tile_coord(p)?;
+        self.promotion_squares()
+            .find(|&(picker_square, _)| picker_square == square)
+            .map(|(_, piece)| piece)
+    }
+
+    /// Where the promotion picker shows each piece: a column starting on the promotion square
+    fn promotion_squares(&self) -> impl Iterator<Item = (Square, Piece)> {
+        let dest = self.game.pending_promotion().map(|(_, dest)| dest);
+        let towards_center = match self.game.board().side_to_move() {
+            chess::Color::White => Square::down,
+            chess::Color::Black => Square::up,
+        };
+
+        PROMOTION_PIECES.iter().scan(dest, move |square, &piece| {
+            let current = (*square)?;
+            *square = towards_center(&current);
+            Some((current, piece))
+        })
+    }
+
+    /// The button of the game over banner at the given point (relative to the center), if any
+    pub fn game_over_choice(&self, p: Point) -> Option<GameOverChoice> {
+        self.game.outcome()?;
+        // Points relative to the center count up, while the buttons are laid out downwards
+        let on_screen = Point::new(p.x(), -p.y());
+        self.game_over_buttons(Point::new(0, 0))
+            .iter()
+            .find(|(rect, _)| rect.contains_point(on_screen))
+            .map(|&(_, choice)| choice)
+    }
+
+    /// The banner shown when the game is over, across the middle of the board, wide enough for the result
+    fn game_over_banner(&self, center: Point) -> Rect {
+        Rect::from_center(center, 8 * self.tile_size, 4 * self.tile_size)
+    }
+
+    /// Where each button sits on the game over banner: new game on the left, quit on the right
+    fn game_over_buttons(&self, center: Point) -> [(Rect, GameOverChoice); 2] {
+        let tile_size = i32::try_from(self.tile_size).unwrap();
+        let button = |x_offset| {
+            Rect::from_center(
+                center.offset(x_offset, tile_size),
+                self.tile_size,
+                self.tile_size,
+            )
+        };
+        [
+            (button(-3 * tile_size / 2), GameOverChoice::NewGame),
+            (button(3 * tile_size / 2), GameOverChoice::Quit),
+        ]
+    }
+
+    /// Shows the winner's king, or both kings for a draw, above a new game and a quit button
+    fn draw_game_over(
+        &self,
+        dest: &mut Renderer,
+        center: Point,
+        outcome: Outcome,
+    ) -> Result<(), String> {
+        let banner = self.game_over_banner(center);
+        dest.set_draw_color(self.theme.banner_background);
+        dest.fill_rect(banner)?;
+        dest.set_draw_color(self.theme.selected);
+        dest.draw_rect(banner)?;
+
+        let tile_size = i32::try_from(self.tile_size).unwrap();
+        let king_at = |x_offset| {
+            Rect::from_center(
+                center.offset(x_offset, -tile_size),
+                self.tile_size,
+                self.tile_size,
+            )
+        };
+        Label::new(&self.font, outcome.to_string(), self.theme.text).draw_at(dest, center)?;
+
+        match outcome.winner() {
+            Some(winner) => self
+                .piece_sprite(Piece::King, winner)
+                .draw_on(dest, king_at(0))?,
+            None => {
+                self.piece_sprite(Piece::King, chess::Color::White)
+                    .draw_on(dest, king_at(-tile_size / 2))?;
+                self.piece_sprite(Piece::King, chess::Color::Black)
+                    .draw_on(dest, king_at(tile_size / 2))?;
             }
         }
 
-        if self.selected_square != square {
-            self.selected_square = square;
-            if let Some(square) = square {
-                println!("Selected {}", square);
+        for &(rect, choice) in self.game_over_buttons(center).iter() {
+            match choice {
+                GameOverChoice::NewGame => {
+                    dest.set_draw_color(self.theme.new_game);
+                    dest.fill_rect(rect)?;
+                    // A pawn, as in the first move of a fresh game
+                    self.piece_sprite(Piece::Pawn, self.game.mode().bottom_side())
+                        .draw_on(dest, rect)?;
+                }
+                GameOverChoice::Quit => {
+                    dest.set_draw_color(self.theme.quit);
+                    dest.fill_rect(rect)?;
+                    dest.set_draw_color(Color::WHITE);
+                    dest.draw_line(rect.top_left(), rect.bottom_right())?;
+                    dest.draw_line(rect.top_right(), rect.bottom_left())?;
+                }
             }
+            dest.set_draw_color(self.theme.selected);
+            dest.draw_rect(rect)?;
         }
+
+        Ok(())
     }
 
-    fn status(&self) -> BoardStatus {
-        self.board.status()
+    /// Layers translucent squares that get smaller towards the middle of `rect`,
+    /// so the color is strongest around the king
+    fn draw_check_glow(&self, dest: &mut Renderer, rect: Rect) -> Result<(), String> {
+        dest.set_draw_color(self.theme.check);
+        for layer in 0..CHECK_GLOW_LAYERS {
+            let inset = layer * CHECK_GLOW_STEP;
+            let size = u32::try_from(i32::try_from(rect.width()).unwrap() - 2 * inset).unwrap();
+            dest.fill_rect(Rect::from_center(rect.center(), size, size))?;
+        }
+        Ok(())
     }
 
-    pub fn is_ongoing(&self) -> bool {
-        self.status() == BoardStatus::Ongoing
+    /// Marks a square the selected piece can move to: a dot for a quiet move,
+    /// a ring around the piece for a capture
+    fn draw_move_marker(
+        &self,
+        dest: &mut Renderer,
+        rect: Rect,
+        capture: bool,
+    ) -> Result<(), String> {
+        let tile_size = f64::from(rect.width());
+        let center = rect.center();
+        if capture {
+            let outer = tile_size / 2.0;
+            dest.set_draw_color(self.theme.capture_ring);
+            draw_ring(dest, center, outer, outer - tile_size * CAPTURE_RING_WIDTH)
+        } else {
+            dest.set_draw_color(self.theme.move_dot);
+            draw_ring(dest, center, tile_size * MOVE_DOT_RADIUS, 0.0)
+        }
     }
 
-    pub fn is_player_winner(&self) -> bool {
-        self.status() == BoardStatus::Checkmate && self.board.side_to_move() == self.player_side
+    /// Draws each player's clock as a bar beside their half of the board,
+    /// filled in proportion to the time they have left
+    fn draw_clocks(&self, dest: &mut Renderer, center: Point) -> Result<(), String> {
+        let clock = match self.game.clock() {
+            Some(clock) => clock,
+            None => return Ok(()),
+        };
+
+        let half_board_size = self.board_size() / 2;
+        let height = u32::try_from(half_board_size).unwrap();
+        let left = center.x() + half_board_size + CLOCK_MARGIN;
+        let bottom_side = if self.flipped {
+            chess::Color::Black
+        } else {
+            chess::Color::White
+        };
+
+        for &color in chess::ALL_COLORS.iter() {
+            let top = if color == bottom_side {
+                center.y()
+            } else {
+                center.y() - half_board_size
+            };
+            let outline = Rect::new(left, top, CLOCK_WIDTH, height);
+
+            let remaining = clock.remaining(color);
+            // Increments can take a clock above its starting time
+            let full = clock.time_control().base.max(remaining);
+            let filled = (f64::from(height) * remaining.as_secs_f64() / full.as_secs_f64()) as u32;
+            let fill_color = if remaining < LOW_TIME {
+                self.theme.clock_low
+            } else if clock.running() == Some(color) {
+                self.theme.clock_running
+            } else {
+                self.theme.clock_stopped
+            };
+            if filled > 0 {
+                dest.set_draw_color(fill_color);
+                dest.fill_rect(Rect::new(
+                    left,
+                    outline.bottom() - i32::try_from(filled).unwrap(),
+                    CLOCK_WIDTH,
+                    filled,
+                ))?;
+            }
+
+            dest.set_draw_color(if clock.running() == Some(color) {
+                self.theme.selected
+            } else {
+                self.theme.text
+            });
+            dest.draw_rect(outline)?;
+
+            // The time sits next to the end of the bar furthest from the middle of the board
+            let label = Label::new(&self.font, clock::format_time(remaining), self.theme.text);
+            let (_, text_height) = label.size()?;
+            let text_top = if color == bottom_side {
+                outline.bottom() - i32::try_from(text_height).unwrap()
+            } else {
+                outline.top()
+            };
+            let text_left = outline.right() + CLOCK_MARGIN;
+            label.draw_on(dest, Rect::new(text_left, text_top, 1, text_height))?;
+        }
+
+        Ok(())
+    }
+
+    /// Labels the files below the board and the ranks to its left
+    fn draw_coordinates(&self, dest: &mut Renderer, center: Point) -> Result<(), String> {
+        let half_board_size = self.board_size() / 2;
+        for index in 0..NUM_FILES {
+            // The squares on the a1-h8 diagonal line up with both their file and rank label
+            let square = Square::make_square(Rank::from_index(index), File::from_index(index));
+            let rect = self.draw_position(square, center);
+
+            let file = (b'a' + u8::try_from(index).unwrap()) as char;
+            let below_board = Point::new(
+                rect.center().x(),
+                center.y() + half_board_size + TEXT_MARGIN,
+            );
+            Label::new(&self.font, file.to_string(), self.theme.text).draw_at(dest, below_board)?;
+
+            let left_of_board = Point::new(
+                center.x() - half_board_size - TEXT_MARGIN,
+                rect.center().y(),
+            );
+            Label::new(&self.font, (index + 1).to_string(), self.theme.text)
+                .draw_at(dest, left_of_board)?;
+        }
+        Ok(())
+    }
+
+    /// What is happening in the game, e.g. whose turn it is
+    fn status_text(&self) -> String {
+        if let Some(plies) = self.reviewed_plies() {
+            return format!(
+                "Reviewing after {}, click the board to return",
+                self.game.history()[plies - 1].san
+            );
+        }
+
+        if let Some(outcome) = self.game.outcome() {
+            return outcome.to_string();
+        }
+
+        let side_to_move = self.game.board().side_to_move();
+        if self.game.is_thinking() {
+            format!("AI is thinking for {:?}", side_to_move)
+        } else if self.game.pending_promotion().is_some() {
+            "Choose a piece to promote to".to_string()
+        } else {
+            format!("{:?} to move", side_to_move)
+        }
+    }
+
+    fn piece_sprite(&self, piece: Piece, color: chess::Color) -> &Sprite<'a> {
+        self.sprites.piece(piece, color)
     }
 
     /// The board size in pixels
-    fn board_size() -> i32 {
-        let tile_size: i32 = TILE_SIZE.into();
+    fn board_size(&self) -> i32 {
+        let tile_size = i32::try_from(self.tile_size).unwrap();
         i32::try_from(NUM_FILES).unwrap() * tile_size
     }
 
-    fn draw_position(square: Square, center: Point) -> Rect {
-        let tile_pos = (square.get_file().to_index(), square.get_rank().to_index());
+    fn draw_position(&self, square: Square, center: Point) -> Rect {
+        let tile_pos = self.view_coords(square);
         let (x, y): (i32, _) = utils::map_tuple(tile_pos, |val| val.try_into().unwrap());
 
-        let tile_size: i32 = TILE_SIZE.into();
+        let tile_size = i32::try_from(self.tile_size).unwrap();
         let (mut pixel_x, mut pixel_y) = utils::map_tuple((x, y), |val| val * tile_size);
 
-        let half_board_size = Self::board_size() / 2;
+        let half_board_size = self.board_size() / 2;
         pixel_x += center.x() - half_board_size;
 
         pixel_y = -pixel_y + (half_board_size - tile_size);
         pixel_y += center.y();
 
-        Rect::new(pixel_x, pixel_y, TILE_SIZE.into(), TILE_SIZE.into())
-    }
-
-    /// Lists all legal moves from the given source
-    fn moves_from(&self, source: Square) -> Vec<ChessMove> {
-        MoveGen::new_legal(&self.board)
-            .filter(|chess_move| chess_move.get_source() == source)
-            .collect()
+        Rect::new(pixel_x, pixel_y, self.tile_size, self.tile_size)
     }
 }
 
 impl Drawable for ChessBoard<'_> {
     fn draw_at(&self, dest: &mut Renderer, center: Point) -> Result<(), String> {
-        let selected_moves = self
-            .selected_square
-            .map(|source| self.moves_from(source))
+        // Lets the translucent theme colors tint what is under them
+        dest.set_blend_mode(BlendMode::Blend);
+
+        let board = self.displayed_board();
+        let reviewing = self.is_reviewing();
+        let last_move = self
+            .displayed_move()
+            .map(|chess_move| (chess_move.get_source(), chess_move.get_dest()));
+        let checked_king = if *board.checkers() == EMPTY {
+            None
+        } else {
+            Some(board.king_square(board.side_to_move()))
+        };
+        let selected_square = self.game.selected_square().filter(|_| !reviewing);
+        let drag = self
+            .drag
+            .as_ref()
+            .filter(|drag| selected_square == Some(drag.from));
+        let selected_moves = selected_square
+            .map(|source| self.game.moves_from(source))
             .unwrap_or_default();
 
         for &square in chess::ALL_SQUARES.iter() {
-            let rect = Self::draw_position(square, center);
+            let rect = self.draw_position(square, center);
 
             let in_board = Point::new(
                 rect.center().x() - center.x(),
                 center.y() - rect.center().y(),
             );
 
-            assert_eq!(Self::tile_coord(in_board), Some(square));
+            assert_eq!(self.tile_coord(in_board), Some(square));
 
             {
                 let x = square.get_rank().to_index();
                 let y = square.get_file().to_index();
                 if (x + y) % 2 == 0 {
-                    // White square
-                    self.sprites[0].draw_on(dest, rect)?;
+                    self.sprites.dark_square.draw_on(dest, rect)?;
                 } else {
-                    // Black square
-                    self.sprites[1].draw_on(dest, rect)?;
+                    self.sprites.light_square.draw_on(dest, rect)?;
                 }
             }
 
-            if let Some(piece) = self.board.piece_on(square) {
-                let color = self.board.color_on(square).unwrap();
-                use chess::Color::*;
-                use chess::Piece::*;
-                match (piece, color) {
-                    (Pawn, Black) => self.sprites[2].draw_on(dest, rect)?,
-                    (Pawn, White) => self.sprites[3].draw_on(dest, rect)?,
-                    (Rook, Black) => self.sprites[4].draw_on(dest, rect)?,
-                    (Rook, White) => self.sprites[5].draw_on(dest, rect)?,
-                    (Knight, Black) => self.sprites[6].draw_on(dest, rect)?,
-                    (Knight, White) => self.sprites[7].draw_on(dest, rect)?,
-                    (Bishop, Black) => self.sprites[8].draw_on(dest, rect)?,
-                    (Bishop, White) => self.sprites[9].draw_on(dest, rect)?,
-                    (Queen, Black) => self.sprites[10].draw_on(dest, rect)?,
-                    (Queen, White) => self.sprites[11].draw_on(dest, rect)?,
-                    (King, Black) => self.sprites[12].draw_on(dest, rect)?,
-                    (King, White) => self.sprites[13].draw_on(dest, rect)?,
-                }
+            if last_move.is_some_and(|(from, to)| square == from || square == to) {
+                dest.set_draw_color(self.theme.last_move);
+                dest.fill_rect(rect)?;
             }
 
-            let is_selected_square = self
-                .selected_square
-                .map(|val| val == square)
-                .unwrap_or(false);
-
-            let is_possible_move = selected_moves
-                .iter()
-                .any(|chess_move| chess_move.get_dest() == square);
+            if checked_king == Some(square) {
+                self.draw_check_glow(dest, rect)?;
+            }
 
-            let highlight_color = if is_selected_square {
-                Some(Color::MAGENTA)
-            } else if is_possible_move {
-                Some(Color::GREEN)
-            } else {
-                None
-            };
+            let is_dragged = drag.map(|drag| drag.from) == Some(square);
+            if let Some(piece) = board.piece_on(square).filter(|_| !is_dragged) {
+                let color = board.color_on(square).unwrap();
+                self.piece_sprite(piece, color).draw_on(dest, rect)?;
+            }
 
-            if let Some(color) = highlight_color {
-                dest.set_draw_color(color);
+            if selected_square == Some(square) {
+                dest.set_draw_color(self.theme.selected);
                 dest.draw_rect(rect)?;
             }
+
+            if let Some(chess_move) = selected_moves
+                .iter()
+                .find(|chess_move| chess_move.get_dest() == square)
+            {
+                self.draw_move_marker(dest, rect, is_capture(board, *chess_move))?;
+            }
+        }
+
+        // Drawn after the board so it covers the squares it sits on
+        let side_to_move = board.side_to_move();
+        for (square, piece) in self.promotion_squares().filter(|_| !reviewing) {
+            let rect = self.draw_position(square, center);
+            dest.set_draw_color(self.theme.promotion_background);
+            dest.fill_rect(rect)?;
+            self.piece_sprite(piece, side_to_move).draw_on(dest, rect)?;
+            dest.set_draw_color(self.theme.selected);
+            dest.draw_rect(rect)?;
+        }
+
+        self.draw_coordinates(dest, center)?;
+        self.draw_clocks(dest, center)?;
+
+        let above_board = center.offset(0, -self.board_size() / 2 - TEXT_MARGIN);
+        Label::new(&self.font, self.status_text(), self.theme.text).draw_at(dest, above_board)?;
+
+        let current_move = match self.reviewed_plies() {
+            Some(plies) => plies.checked_sub(1),
+            None => self.game.history().len().checked_sub(1),
+        };
+        MoveListView {
+            list: &self.move_list,
+            game: &self.game,
+            font: &self.font,
+            theme: &self.theme,
+            current: current_move,
+        }
+        .draw_on(dest, self.move_list_area(center))?;
+
+        if let Some(outcome) = self.game.outcome().filter(|_| !reviewing) {
+            self.draw_game_over(dest, center, outcome)?;
+        }
+
+        if self.game.is_thinking() {
+            let board_size = self.board_size().try_into().unwrap();
+            dest.set_draw_color(self.theme.thinking);
+            dest.draw_rect(Rect::from_center(center, board_size, board_size))?;
         }
 
         for (i, sprite) in self.sprites.iter().enumerate() {
             sprite.draw_on(
                 dest,
                 Rect::new(
-                    (32 * i).try_into().unwrap(),
+                    (MIN_TILE_SIZE * u32::try_from(i).unwrap())
+                        .try_into()
+                        .unwrap(),
                     0,
-                    TILE_SIZE.into(),
-                    TILE_SIZE.into(),
+                    MIN_TILE_SIZE,
+                    MIN_TILE_SIZE,
                 ),
             )?;
         }
 
+        // Drawn last so the piece floats above everything it is dragged across
+        if let Some(drag) = drag {
+            if let (Some(piece), Some(color)) =
+                (board.piece_on(drag.from), board.color_on(drag.from))
+            {
+                let on_screen = center.offset(drag.cursor.x(), -drag.cursor.y());
+                let rect = Rect::from_center(on_screen, self.tile_size, self.tile_size);
+                self.piece_sprite(piece, color).draw_on(dest, rect)?;
+            }
+        }
+
         Ok(())
     }
 }
+
+/// Whether `chess_move` takes a piece, including en passant
+fn is_capture(board: &Board, chess_move: ChessMove) -> bool {
+    let (source, dest) = (chess_move.get_source(), chess_move.get_dest());
+    board.piece_on(dest).is_some()
+        || (board.piece_on(source) == Some(Piece::Pawn) && source.get_file() != dest.get_file())
+}
+
+/// Fills the pixels between the circles of radius `inner` and `outer` around `center`,
+/// one row at a time so that translucent colors cover every pixel only once.
+/// An `inner` radius of 0 gives a disc.
+fn draw_ring(dest: &mut Renderer, center: Point, outer: f64, inner: f64) -> Result<(), String> {
+    let rows = outer.ceil() as i32;
+    for dy in -rows..=rows {
+        // Sampling the middle of the row keeps the shape symmetric
+        let y = f64::from(dy) + 0.5;
+        if y.abs() > outer {
+            continue;
+        }
+        let outer_x = (outer * outer - y * y).sqrt().round() as i32;
+        let row = center.y() + dy;
+
+        if y.abs() < inner {
+            let inner_x = (inner * inner - y * y).sqrt().round() as i32;
+            for &(from, to) in [(-outer_x, -inner_x - 1), (inner_x, outer_x - 1)].iter() {
+                if from <= to {
+                    dest.draw_line((center.x() + from, row), (center.x() + to, row))?;
+                }
+            }
+        } else if outer_x > 0 {
+            dest.draw_line((center.x() - outer_x, row), (center.x() + outer_x - 1, row))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,244 @@
+//! Chess clocks and the time controls they run under
+
+use chess::Color;
+
+use std::{
+    fmt,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+/// Time given back to a player for every move they make
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bonus {
+    /// Added to the clock after every move (Fischer)
+    Increment(Duration),
+    /// The clock only starts running once this much of the move has passed (simple delay)
+    Delay(Duration),
+}
+
+/// How much time each player gets, e.g. `5+3` for five minutes plus three seconds per move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub bonus: Bonus,
+}
+
+impl TimeControl {
+    pub const BLITZ: TimeControl = TimeControl::increment(5, 3);
+    pub const RAPID: TimeControl = TimeControl::increment(15, 10);
+    pub const CLASSICAL: TimeControl = TimeControl::increment(90, 30);
+
+    /// `minutes` per player, plus `seconds` added after every move
+    pub const fn increment(minutes: u64, seconds: u64) -> Self {
+        Self {
+            base: Duration::from_secs(minutes * 60),
+            bonus: Bonus::Increment(Duration::from_secs(seconds)),
+        }
+    }
+
+    /// `minutes` per player, with the clock waiting `seconds` before it starts every move
+    pub const fn delay(minutes: u64, seconds: u64) -> Self {
+        Self {
+            base: Duration::from_secs(minutes * 60),
+            bonus: Bonus::Delay(Duration::from_secs(seconds)),
+        }
+    }
+
+    /// The time a player can count on getting back for every move
+    pub fn time_per_move(&self) -> Duration {
+        match self.bonus {
+            Bonus::Increment(time) | Bonus::Delay(time) => time,
+        }
+    }
+}
+
+impl fmt::Display for TimeControl {
+    /// Writes minutes and seconds with `+` for an increment and `d` for a delay, e.g. `5+3` or `5d3`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (separator, time) = match self.bonus {
+            Bonus::Increment(time) => ('+', time),
+            Bonus::Delay(time) => ('d', time),
+        };
+        let base = self.base.as_secs_f64() / 60.0;
+        write!(f, "{}{}{}", base, separator, time.as_secs())
+    }
+}
+
+impl FromStr for TimeControl {
+    type Err = String;
+
+    /// Reads the notation written by `Display`, or one of `blitz`, `rapid` and `classical`.
+    /// The minutes may be fractional, e.g. `0.5+0` for a thirty second game.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_lowercase().as_str() {
+            "blitz" => return Ok(Self::BLITZ),
+            "rapid" => return Ok(Self::RAPID),
+            "classical" => return Ok(Self::CLASSICAL),
+            _ => {}
+        }
+
+        let invalid = || format!("Invalid time control {}, expected e.g. 5+3 or 5d3", text);
+        let (minutes, seconds, bonus): (_, _, fn(Duration) -> Bonus) =
+            if let Some((minutes, seconds)) = text.split_once('+') {
+                (minutes, seconds, Bonus::Increment)
+            } else if let Some((minutes, seconds)) = text.split_once('d') {
+                (minutes, seconds, Bonus::Delay)
+            } else {
+                (text, "0", Bonus::Increment)
+            };
+
+        let minutes: f64 = minutes.parse().map_err(|_| invalid())?;
+        let seconds: u64 = seconds.parse().map_err(|_| invalid())?;
+        if !minutes.is_finite() || minutes <= 0.0 {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            // Finite but huge numbers of minutes do not fit in a `Duration`
+            base: Duration::try_from_secs_f64(minutes * 60.0).map_err(|_| invalid())?,
+            bonus: bonus(Duration::from_secs(seconds)),
+        })
+    }
+}
+
+/// A clock for each player, of which at most one runs at a time
+#[derive(Clone, Debug)]
+pub struct ChessClock {
+    control: TimeControl,
+    /// Time left for White and Black when their clock was last stopped
+    remaining: [Duration; 2],
+    /// The player whose clock is running, and since when
+    running: Option<(Color, Instant)>,
+}
+
+impl ChessClock {
+    /// Both clocks start with the full time and neither is running
+    pub fn new(control: TimeControl) -> Self {
+        Self {
+            control,
+            remaining: [control.base; 2],
+            running: None,
+        }
+    }
+
+    pub fn time_control(&self) -> TimeControl {
+        self.control
+    }
+
+    /// The time `color` has left, counting the move in progress
+    pub fn remaining(&self, color: Color) -> Duration {
+        let stored = self.remaining[color.to_index()];
+        match self.running {
+            Some((running, since)) if running == color => {
+                stored.saturating_sub(self.charged(since.elapsed()))
+            }
+            _ => stored,
+        }
+    }
+
+    /// The time White and Black have left, counting the move in progress
+    pub fn times(&self) -> [Duration; 2] {
+        [self.remaining(Color::White), self.remaining(Color::Black)]
+    }
+
+    /// Stops both clocks and sets the time White and Black have left,
+    /// e.g. to what it was before a move that was taken back
+    pub fn set_times(&mut self, times: [Duration; 2]) {
+        self.running = None;
+        self.remaining = times;
+    }
+
+    /// The player whose clock is running
+    pub fn running(&self) -> Option<Color> {
+        self.running.map(|(color, _)| color)
+    }
+
+    /// The player who has run out of time, if any
+    pub fn flagged(&self) -> Option<Color> {
+        chess::ALL_COLORS
+            .iter()
+            .copied()
+            .find(|&color| self.remaining(color) == Duration::ZERO)
+    }
+
+    /// Starts the clock of `color`, stopping the other one without giving it its bonus,
+    /// e.g. at the start of the game or after a move was taken back
+    pub fn start(&mut self, color: Color) {
+        self.stop();
+        self.running = Some((color, Instant::now()));
+    }
+
+    /// Ends the move of the player whose clock is running,
+    /// giving them their increment and starting the opponent's clock
+    pub fn press(&mut self) {
+        if let Some(color) = self.running() {
+            self.stop();
+            if let Bonus::Increment(increment) = self.control.bonus {
+                if self.remaining[color.to_index()] > Duration::ZERO {
+                    let remaining = &mut self.remaining[color.to_index()];
+                    *remaining = remaining.saturating_add(increment);
+                }
+            }
+            self.start(!color);
+        }
+    }
+
+    /// Stops whichever clock is running, keeping the time it used
+    pub fn stop(&mut self) {
+        if let Some((color, _)) = self.running {
+            self.remaining[color.to_index()] = self.remaining(color);
+            self.running = None;
+        }
+    }
+
+    /// How much of `elapsed` is taken off the clock, which is less than all of it during a delay
+    fn charged(&self, elapsed: Duration) -> Duration {
+        match self.control.bonus {
+            Bonus::Delay(delay) => elapsed.saturating_sub(delay),
+            Bonus::Increment(_) => elapsed,
+        }
+    }
+}
+
+/// Writes the time on a clock as `m:ss`, or with tenths of a second (`0:09.5`) when it is low
+pub fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds < 10 {
+        format!("0:{:02}.{}", seconds, time.subsec_millis() / 100)
+    } else if seconds < 3600 {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_increments_delays_and_names() {
+        let control: TimeControl = "5+3".parse().unwrap();
+        assert_eq!(control.base, Duration::from_secs(300));
+        assert_eq!(control.bonus, Bonus::Increment(Duration::from_secs(3)));
+
+        let control: TimeControl = "0.5d2".parse().unwrap();
+        assert_eq!(control.base, Duration::from_secs(30));
+        assert_eq!(control.bonus, Bonus::Delay(Duration::from_secs(2)));
+
+        assert_eq!("Blitz".parse(), Ok(TimeControl::BLITZ));
+    }
+
+    #[test]
+    fn rejects_times_that_do_not_fit() {
+        for text in ["1e300+0", "inf+0", "NaN+0", "0+5", "-1+0", "5+x", "5+-1"].iter() {
+            assert!(text.parse::<TimeControl>().is_err(), "{}", text);
+        }
+    }
+}
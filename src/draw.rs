@@ -0,0 +1,211 @@
+//! Draws that the chess crate does not detect by itself:
+//! repetition, the fifty-move rule and insufficient material
+
+use chess::{BitBoard, Board, ChessMove, Piece, EMPTY};
+
+use std::fmt;
+
+/// Plies without a capture or pawn move after which the game is drawn
+const FIFTY_MOVE_PLIES: u32 = 100;
+
+/// How often a position has to occur for the game to be drawn
+const REPETITIONS: usize = 3;
+
+/// The light squares, starting with b1 and a2
+const LIGHT_SQUARES: BitBoard = BitBoard(0x55AA_55AA_55AA_55AA);
+
+/// Why a game that is not checkmate or stalemate was drawn
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrawReason {
+    Repetition,
+    FiftyMoves,
+    InsufficientMaterial,
+}
+
+impl fmt::Display for DrawReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            DrawReason::Repetition => "threefold repetition",
+            DrawReason::FiftyMoves => "fifty-move rule",
+            DrawReason::InsufficientMaterial => "insufficient material",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// Whether playing `chess_move` on `board` is a capture or pawn move,
+/// which resets the fifty-move count and makes earlier positions unreachable
+pub fn is_irreversible(board: &Board, chess_move: ChessMove) -> bool {
+    board.piece_on(chess_move.get_source()) == Some(Piece::Pawn)
+        || board.piece_on(chess_move.get_dest()).is_some()
+}
+
+/// Checks whether the last of `positions` is drawn.
+/// `positions` are the positions since the last irreversible move, oldest first,
+/// so their count (minus one) is the number of plies on the fifty-move count.
+/// `halfmove_clock` is how many plies had already been counted before the first of them.
+pub fn draw_reason(positions: &[Board], halfmove_clock: u32) -> Option<DrawReason> {
+    let current = positions.last()?;
+
+    if insufficient_material(current) {
+        return Some(DrawReason::InsufficientMaterial);
+    }
+
+    let plies = halfmove_clock + positions.len() as u32 - 1;
+    if plies >= FIFTY_MOVE_PLIES {
+        return Some(DrawReason::FiftyMoves);
+    }
+
+    // The hash covers the side to move, castling rights and en passant,
+    // so equal hashes mean the same position in the sense of the rules
+    let occurrences = positions
+        .iter()
+        .filter(|board| board.get_hash() == current.get_hash())
+        .count();
+    if occurrences >= REPETITIONS {
+        return Some(DrawReason::Repetition);
+    }
+
+    None
+}
+
+/// Whether neither side can possibly deliver checkmate:
+/// bare kings, a single minor piece, or only bishops all on squares of one color
+pub fn insufficient_material(board: &Board) -> bool {
+    let heavy =
+        *board.pieces(Piece::Pawn) | *board.pieces(Piece::Rook) | *board.pieces(Piece::Queen);
+    if heavy != EMPTY {
+        return false;
+    }
+
+    let knights = *board.pieces(Piece::Knight);
+    let bishops = *board.pieces(Piece::Bishop);
+    let minors = (knights | bishops).popcnt();
+    if minors <= 1 {
+        return true;
+    }
+
+    knights == EMPTY && (bishops & LIGHT_SQUARES == EMPTY || bishops & !LIGHT_SQUARES == EMPTY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn board(fen: &str) -> Board {
+        Board::from_str(fen).unwrap()
+    }
+
+    /// `start` followed by the position after each of `moves`
+    fn positions(start: &str, moves: &[&str]) -> Vec<Board> {
+        let mut positions = vec![board(start)];
+        for text in moves {
+            let chess_move = ChessMove::from_str(text).unwrap();
+            positions.push(positions.last().unwrap().make_move_new(chess_move));
+        }
+        positions
+    }
+
+    #[test]
+    fn light_squares() {
+        let light = |square: &str| {
+            let square = chess::Square::from_str(square).unwrap();
+            LIGHT_SQUARES & BitBoard::from_square(square) != EMPTY
+        };
+        assert!(light("b1") && light("a2") && light("h1") && light("a8"));
+        assert!(!light("a1") && !light("h8") && !light("e1") && !light("d8"));
+        assert_eq!(LIGHT_SQUARES.popcnt(), 32);
+    }
+
+    #[test]
+    fn threefold_repetition() {
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+        let twice = positions(start, &shuffle);
+        assert_eq!(draw_reason(&twice, 0), None);
+
+        let thrice = positions(start, &[&shuffle[..], &shuffle[..]].concat());
+        assert_eq!(draw_reason(&thrice, 0), Some(DrawReason::Repetition));
+    }
+
+    #[test]
+    fn lost_castling_rights_break_the_repetition() {
+        // The rooks come back, but without the right to castle short
+        let shuffle = ["h1g1", "h8g8", "g1h1", "g8h8"];
+        let start = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+
+        let same_squares_thrice = positions(start, &[&shuffle[..], &shuffle[..]].concat());
+        assert_eq!(draw_reason(&same_squares_thrice, 0), None);
+
+        let same_rights_thrice = positions(start, &[&shuffle[..], &shuffle, &shuffle].concat());
+        assert_eq!(
+            draw_reason(&same_rights_thrice, 0),
+            Some(DrawReason::Repetition)
+        );
+    }
+
+    #[test]
+    fn fifty_moves() {
+        let start = "4k3/8/8/8/8/8/8/R3K3 w - - 0 1";
+        assert_eq!(draw_reason(&positions(start, &[]), 99), None);
+        assert_eq!(
+            draw_reason(&positions(start, &[]), 100),
+            Some(DrawReason::FiftyMoves)
+        );
+        assert_eq!(
+            draw_reason(&positions(start, &["a1a2"]), 99),
+            Some(DrawReason::FiftyMoves)
+        );
+        assert_eq!(draw_reason(&positions(start, &["a1a2"]), 98), None);
+    }
+
+    #[test]
+    fn bare_kings_and_single_minor_pieces() {
+        assert!(insufficient_material(&board(
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1"
+        )));
+        assert!(insufficient_material(&board(
+            "4k3/8/8/8/8/8/8/2B1K3 w - - 0 1"
+        )));
+        assert!(insufficient_material(&board(
+            "4k3/8/8/8/8/8/8/1N2K3 b - - 0 1"
+        )));
+        assert!(insufficient_material(&board(
+            "4kn2/8/8/8/8/8/8/4K3 w - - 0 1"
+        )));
+        assert_eq!(
+            draw_reason(&positions("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1", &[]), 0),
+            Some(DrawReason::InsufficientMaterial)
+        );
+    }
+
+    #[test]
+    fn bishops_on_one_color() {
+        // c1 and f8 are both dark squares
+        assert!(insufficient_material(&board(
+            "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1"
+        )));
+        // c1 is dark and c8 is light
+        assert!(!insufficient_material(&board(
+            "2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1"
+        )));
+        assert!(!insufficient_material(&board(
+            "4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1"
+        )));
+    }
+
+    #[test]
+    fn any_pawn_rook_or_queen_can_mate() {
+        assert!(!insufficient_material(&board(
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"
+        )));
+        assert!(!insufficient_material(&board(
+            "4k3/8/8/8/8/8/8/R3K3 w - - 0 1"
+        )));
+        assert!(!insufficient_material(&board(
+            "3qk3/8/8/8/8/8/8/4K3 w - - 0 1"
+        )));
+    }
+}
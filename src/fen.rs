@@ -0,0 +1,388 @@
+//! Forsyth-Edwards Notation for describing positions
+
+use chess::{Board, BoardBuilder, Piece, ALL_COLORS, ALL_SQUARES};
+
+use std::{convert::TryFrom, fmt, str::FromStr};
+
+/// A board along with the move counters that FEN records but `Board` does not
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub board: Board,
+    /// Plies since the last capture or pawn move, for the fifty-move rule
+    pub halfmove_clock: u32,
+    /// Starts at 1 and goes up after every Black move
+    pub fullmove_number: u32,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self {
+            board: Board::default(),
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // `Board` always writes "0 1" for the counters, so replace them with the real ones
+        let board = self.board.to_string();
+        let fields: Vec<_> = board.split_whitespace().take(3).collect();
+        // It also writes the square of the pawn that can be taken en passant,
+        // where FEN wants the square behind it
+        let en_passant = self
+            .board
+            .en_passant()
+            .and_then(|square| match self.board.side_to_move() {
+                chess::Color::White => square.up(),
+                chess::Color::Black => square.down(),
+            })
+            .map_or_else(|| "-".to_string(), |square| square.to_string());
+        write!(
+            f,
+            "{} {} {} {}",
+            fields.join(" "),
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+}
+
+/// Why a FEN string was rejected
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FenError {
+    WrongFieldCount(usize),
+    WrongRankCount(usize),
+    /// A rank (numbered 1 to 8) does not describe exactly 8 squares
+    WrongRankLength(usize),
+    InvalidPiece(char),
+    InvalidSideToMove(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+    /// A side has no king or more than one
+    KingCount(chess::Color, usize),
+    PawnOnBackRank,
+    /// The chess crate's own sanity checks failed
+    ImpossiblePosition,
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use FenError::*;
+        match self {
+            WrongFieldCount(count) => write!(
+                f,
+                "expected 6 space-separated fields (or 4 without move counters), found {}",
+                count
+            ),
+            WrongRankCount(count) => {
+                write!(f, "expected 8 ranks separated by '/', found {}", count)
+            }
+            WrongRankLength(rank) => write!(f, "rank {} does not have exactly 8 squares", rank),
+            InvalidPiece(c) => write!(f, "'{}' is not a piece or a number of empty squares", c),
+            InvalidSideToMove(side) => {
+                write!(f, "side to move must be 'w' or 'b', not '{}'", side)
+            }
+            InvalidCastling(castling) => write!(
+                f,
+                "castling rights must be '-' or some of 'KQkq', not '{}'",
+                castling
+            ),
+            InvalidEnPassant(square) => write!(
+                f,
+                "en passant target must be '-' or a square on the 3rd or 6th rank, not '{}'",
+                square
+            ),
+            InvalidHalfmoveClock(clock) => write!(
+                f,
+                "halfmove clock must be a non-negative number, not '{}'",
+                clock
+            ),
+            InvalidFullmoveNumber(number) => write!(
+                f,
+                "fullmove number must be a positive number, not '{}'",
+                number
+            ),
+            KingCount(color, count) => {
+                write!(f, "{:?} must have exactly one king, not {}", color, count)
+            }
+            PawnOnBackRank => write!(f, "pawns cannot be on the 1st or 8th rank"),
+            ImpossiblePosition => write!(
+                f,
+                "the position is impossible (is the side not to move in check, \
+                 or do the castling rights not match the kings and rooks?)"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl FromStr for Position {
+    type Err = FenError;
+
+    /// Parses a FEN string.
+    /// Unlike `Board::from_str`, this explains what is wrong with invalid input
+    /// and rejects positions that would make the chess crate panic.
+    fn from_str(fen: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<_> = fen.split_whitespace().collect();
+        if fields.len() != 4 && fields.len() != 6 {
+            return Err(FenError::WrongFieldCount(fields.len()));
+        }
+
+        check_placement(fields[0])?;
+
+        if !matches!(fields[1], "w" | "b") {
+            return Err(FenError::InvalidSideToMove(fields[1].to_string()));
+        }
+
+        let castling = fields[2];
+        let valid_castling = castling == "-"
+            || (!castling.is_empty()
+                && castling.chars().all(|c| "KQkq".contains(c))
+                && castling
+                    .chars()
+                    .enumerate()
+                    .all(|(i, c)| !castling[i + 1..].contains(c)));
+        if !valid_castling {
+            return Err(FenError::InvalidCastling(castling.to_string()));
+        }
+
+        let en_passant = fields[3];
+        let valid_en_passant = en_passant == "-"
+            || (en_passant.len() == 2
+                && matches!(en_passant.as_bytes()[0], b'a'..=b'h')
+                && matches!(en_passant.as_bytes()[1], b'3' | b'6'));
+        if !valid_en_passant {
+            return Err(FenError::InvalidEnPassant(en_passant.to_string()));
+        }
+
+        let (halfmove_clock, fullmove_number) = if fields.len() == 6 {
+            let halfmove_clock = fields[4]
+                .parse()
+                .map_err(|_| FenError::InvalidHalfmoveClock(fields[4].to_string()))?;
+            let fullmove_number = fields[5]
+                .parse()
+                .ok()
+                .filter(|&number| number > 0)
+                .ok_or_else(|| FenError::InvalidFullmoveNumber(fields[5].to_string()))?;
+            (halfmove_clock, fullmove_number)
+        } else {
+            (0, 1)
+        };
+
+        let builder = BoardBuilder::from_str(&fields[..4].join(" "))
+            .map_err(|_| FenError::ImpossiblePosition)?;
+
+        // Building a `Board` without exactly one king per side panics inside the chess crate
+        for &color in ALL_COLORS.iter() {
+            let kings = ALL_SQUARES
+                .iter()
+                .filter(|&&square| builder[square] == Some((Piece::King, color)))
+                .count();
+            if kings != 1 {
+                return Err(FenError::KingCount(color, kings));
+            }
+        }
+
+        let board = Board::try_from(builder).map_err(|_| FenError::ImpossiblePosition)?;
+
+        Ok(Self {
+            board,
+            halfmove_clock,
+            fullmove_number,
+        })
+    }
+}
+
+/// Checks the piece placement field, rank by rank
+fn check_placement(placement: &str) -> Result<(), FenError> {
+    let ranks: Vec<_> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::WrongRankCount(ranks.len()));
+    }
+
+    // FEN lists the 8th rank first
+    for (index, rank) in ranks.iter().enumerate() {
+        let rank_number = 8 - index;
+        let mut squares = 0;
+        for c in rank.chars() {
+            match c {
+                '1'..='8' => squares += c.to_digit(10).unwrap(),
+                'p' | 'P' if rank_number == 1 || rank_number == 8 => {
+                    return Err(FenError::PawnOnBackRank)
+                }
+                'p' | 'n' | 'b' | 'r' | 'q' | 'k' | 'P' | 'N' | 'B' | 'R' | 'Q' | 'K' => {
+                    squares += 1
+                }
+                _ => return Err(FenError::InvalidPiece(c)),
+            }
+        }
+        if squares != 8 {
+            return Err(FenError::WrongRankLength(rank_number));
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses a FEN string into a board, ignoring the move counters
+pub fn parse(fen: &str) -> Result<Board, FenError> {
+    Position::from_str(fen).map(|position| position.board)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chess::Color;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    fn error(fen: &str) -> FenError {
+        Position::from_str(fen).unwrap_err()
+    }
+
+    #[test]
+    fn wrong_field_count() {
+        assert_eq!(error(""), FenError::WrongFieldCount(0));
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w -"),
+            FenError::WrongFieldCount(3)
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - - 0"),
+            FenError::WrongFieldCount(5)
+        );
+    }
+
+    #[test]
+    fn wrong_rank_count() {
+        assert_eq!(
+            error("4k3/8/8/8/8/8/4K3 w - - 0 1"),
+            FenError::WrongRankCount(7)
+        );
+    }
+
+    #[test]
+    fn wrong_rank_length() {
+        assert_eq!(
+            error("4k3/8/8/8/8/8/45/4K3 w - - 0 1"),
+            FenError::WrongRankLength(2)
+        );
+        assert_eq!(
+            error("4k4/8/8/8/8/8/8/4K3 w - - 0 1"),
+            FenError::WrongRankLength(8)
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K2 w - - 0 1"),
+            FenError::WrongRankLength(1)
+        );
+    }
+
+    #[test]
+    fn invalid_piece() {
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K2X w - - 0 1"),
+            FenError::InvalidPiece('X')
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/0K7 w - - 0 1"),
+            FenError::InvalidPiece('0')
+        );
+    }
+
+    #[test]
+    fn invalid_side_to_move() {
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 white - - 0 1"),
+            FenError::InvalidSideToMove("white".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_castling() {
+        for castling in &["KX", "KK", "QKq-"] {
+            let fen = format!("r3k2r/8/8/8/8/8/8/R3K2R w {} - 0 1", castling);
+            assert_eq!(error(&fen), FenError::InvalidCastling(castling.to_string()));
+        }
+        // Rights for a rook that is not there
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w K - 0 1"),
+            FenError::ImpossiblePosition
+        );
+    }
+
+    #[test]
+    fn invalid_en_passant() {
+        for square in &["e4", "i6", "e", "e66"] {
+            let fen = format!("4k3/8/8/8/8/8/8/4K3 w - {} 0 1", square);
+            assert_eq!(error(&fen), FenError::InvalidEnPassant(square.to_string()));
+        }
+    }
+
+    #[test]
+    fn invalid_counters() {
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - - -1 1"),
+            FenError::InvalidHalfmoveClock("-1".to_string())
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - - 0 0"),
+            FenError::InvalidFullmoveNumber("0".to_string())
+        );
+    }
+
+    #[test]
+    fn missing_or_extra_king() {
+        assert_eq!(
+            error("8/8/8/8/8/8/8/4K3 w - - 0 1"),
+            FenError::KingCount(Color::Black, 0)
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"),
+            FenError::KingCount(Color::White, 2)
+        );
+    }
+
+    #[test]
+    fn pawn_on_back_rank() {
+        assert_eq!(
+            error("4k2P/8/8/8/8/8/8/4K3 w - - 0 1"),
+            FenError::PawnOnBackRank
+        );
+    }
+
+    #[test]
+    fn side_not_to_move_in_check() {
+        // White to move while the black king is attacked by the rook
+        assert_eq!(
+            error("k7/8/8/8/8/8/8/R3K3 w - - 0 1"),
+            FenError::ImpossiblePosition
+        );
+    }
+
+    #[test]
+    fn four_fields_get_default_counters() {
+        let position = Position::from_str("4k3/8/8/8/8/8/8/4K3 b - -").unwrap();
+        assert_eq!(position.halfmove_clock, 0);
+        assert_eq!(position.fullmove_number, 1);
+        assert_eq!(position.to_string(), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+    }
+
+    #[test]
+    fn round_trips_through_display() {
+        for fen in &[
+            START,
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/8/4k3/8/8/3K4/8/8 w - - 37 80",
+        ] {
+            assert_eq!(Position::from_str(fen).unwrap().to_string(), *fen);
+        }
+        assert_eq!(Position::default().to_string(), START);
+    }
+}
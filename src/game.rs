@@ -0,0 +1,1038 @@
+use chess::{Board, BoardStatus, ChessMove, Color, MoveGen, Piece, Square};
+
+use std::{convert::TryFrom, fmt, str::FromStr, sync::Arc, time::Duration};
+
+use crate::{
+    ai::{
+        book::BookSettings, tablebase::Tablebase, AIState, AIWorker, Difficulty, Progress,
+        SearchLimits,
+    },
+    clock::{ChessClock, TimeControl},
+    draw::{self, DrawReason},
+    fen::Position,
+    pgn::PgnGame,
+    san,
+};
+
+/// How long the AI may think about each move
+const AI_MOVE_TIME: Duration = Duration::from_secs(2);
+
+/// Names recorded for each side in saved games
+const PLAYER_NAME: &str = "Player";
+const AI_NAME: &str = concat!(env!("CARGO_PKG_NAME"), " AI");
+
+/// Who controls each side
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameMode {
+    /// The human plays White against the AI
+    HumanVsAi,
+    /// The AI plays White against the human
+    AiVsHuman,
+    /// Two people take turns on the same machine
+    HumanVsHuman,
+    /// The AI plays both sides
+    AiVsAi,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 4] = [
+        GameMode::HumanVsAi,
+        GameMode::AiVsHuman,
+        GameMode::HumanVsHuman,
+        GameMode::AiVsAi,
+    ];
+
+    /// Whether a person moves the pieces of `color`
+    pub fn is_human(self, color: Color) -> bool {
+        match (self, color) {
+            (GameMode::HumanVsHuman, _) => true,
+            (GameMode::AiVsAi, _) => false,
+            (GameMode::HumanVsAi, side) => side == Color::White,
+            (GameMode::AiVsHuman, side) => side == Color::Black,
+        }
+    }
+
+    /// The side that is best shown at the bottom of the board
+    pub fn bottom_side(self) -> Color {
+        match self {
+            GameMode::AiVsHuman => Color::Black,
+            _ => Color::White,
+        }
+    }
+
+    fn from_sides(white_is_human: bool, black_is_human: bool) -> Self {
+        match (white_is_human, black_is_human) {
+            (true, false) => GameMode::HumanVsAi,
+            (false, true) => GameMode::AiVsHuman,
+            (true, true) => GameMode::HumanVsHuman,
+            (false, false) => GameMode::AiVsAi,
+        }
+    }
+
+    /// The name recorded for `color` in saved games
+    fn player_name(self, color: Color) -> &'static str {
+        if self.is_human(color) {
+            PLAYER_NAME
+        } else {
+            AI_NAME
+        }
+    }
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            GameMode::HumanVsAi => "human-ai",
+            GameMode::AiVsHuman => "ai-human",
+            GameMode::HumanVsHuman => "human-human",
+            GameMode::AiVsAi => "ai-ai",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    /// Reads the names written by `Display`, e.g. `human-ai` for a human playing White
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|mode| mode.to_string() == text.to_lowercase())
+            .ok_or_else(|| format!("Unknown game mode {}", text))
+    }
+}
+
+/// How a finished game ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Checkmate {
+        winner: Color,
+    },
+    /// The loser's flag fell
+    Timeout {
+        winner: Color,
+    },
+    Stalemate,
+    Draw(DrawReason),
+}
+
+impl Outcome {
+    /// Checks whether the game has ended in the last of `positions`, by the rules or by a draw.
+    /// `positions` and `halfmove_clock` are as for `draw::draw_reason`.
+    pub fn of(positions: &[Board], halfmove_clock: u32) -> Option<Self> {
+        let board = positions.last()?;
+        match board.status() {
+            BoardStatus::Checkmate => Some(Outcome::Checkmate {
+                winner: !board.side_to_move(),
+            }),
+            BoardStatus::Stalemate => Some(Outcome::Stalemate),
+            BoardStatus::Ongoing => draw::draw_reason(positions, halfmove_clock).map(Outcome::Draw),
+        }
+    }
+
+    pub fn winner(self) -> Option<Color> {
+        match self {
+            Outcome::Checkmate { winner } | Outcome::Timeout { winner } => Some(winner),
+            _ => None,
+        }
+    }
+
+    /// The value of the PGN `Termination` tag: `normal` for endings by the rules of chess,
+    /// `time forfeit` when a flag fell
+    pub fn termination(self) -> &'static str {
+        match self {
+            Outcome::Timeout { .. } => "time forfeit",
+            Outcome::Checkmate { .. } | Outcome::Stalemate | Outcome::Draw(_) => "normal",
+        }
+    }
+
+    /// The result in PGN notation: `1-0`, `0-1` or `1/2-1/2`
+    pub fn result(self) -> &'static str {
+        match self.winner() {
+            Some(Color::White) => "1-0",
+            Some(Color::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Checkmate { winner } => write!(f, "{:?} wins by checkmate", winner),
+            Outcome::Timeout { winner } => write!(f, "{:?} wins on time", winner),
+            Outcome::Stalemate => write!(f, "Draw by stalemate"),
+            Outcome::Draw(reason) => write!(f, "Draw by {}", reason),
+        }
+    }
+}
+
+/// Something that happened in a game that a front-end may want to tell the player about.
+/// Collected by the game until `ChessGame::take_events` is called.
+#[derive(Clone, Debug)]
+pub enum GameEvent {
+    Selected(Square),
+    /// Several pieces can be promoted to, so the player has to pick one
+    ChoosePromotion,
+    IllegalPromotion(ChessMove),
+    PromotionCancelled,
+    PlayerMoved(ChessMove),
+    AiThinking,
+    AiProgress(Progress),
+    AiMoved(ChessMove),
+    AiCancelled,
+    /// The player of this color ran out of time
+    Flagged(Color),
+    NothingToUndo,
+    /// A move, in SAN, was taken back
+    TookBack(String),
+    NothingToRedo,
+    /// A move, in SAN, was played again
+    Replayed(String),
+}
+
+impl fmt::Display for GameEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameEvent::Selected(square) => write!(f, "Selected {}", square),
+            GameEvent::ChoosePromotion => write!(f, "Choose a piece to promote to"),
+            GameEvent::IllegalPromotion(chess_move) => {
+                write!(f, "{} is not a legal promotion", chess_move)
+            }
+            GameEvent::PromotionCancelled => write!(f, "Promotion cancelled"),
+            GameEvent::PlayerMoved(chess_move) => write!(f, "Player is doing {}", chess_move),
+            GameEvent::AiThinking => write!(f, "AI is calculating move"),
+            GameEvent::AiProgress(progress) => write!(f, "{}", progress),
+            GameEvent::AiMoved(chess_move) => write!(f, "AI plays {}", chess_move),
+            GameEvent::AiCancelled => write!(f, "Cancelling AI search"),
+            GameEvent::Flagged(loser) => write!(f, "{:?} ran out of time", loser),
+            GameEvent::NothingToUndo => write!(f, "Nothing to undo"),
+            GameEvent::TookBack(san) => write!(f, "Taking back {}", san),
+            GameEvent::NothingToRedo => write!(f, "Nothing to redo"),
+            GameEvent::Replayed(san) => write!(f, "Replaying {}", san),
+        }
+    }
+}
+
+/// A move that has been played, along with how it was written
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub chess_move: ChessMove,
+    /// The move in Standard Algebraic Notation
+    pub san: String,
+    /// The position the move was played in
+    pub before: Board,
+    /// The time White and Black had left right after the move, in games with a clock
+    pub clocks: Option<[Duration; 2]>,
+}
+
+/// A game between any mix of people and the AI, independent of how it is displayed
+pub struct ChessGame {
+    board: Board,
+    /// The position the game started from
+    start: Position,
+    /// Every move played since `start`
+    history: Vec<HistoryEntry>,
+    /// Moves taken back with `undo`, most recently undone last
+    redo_stack: Vec<HistoryEntry>,
+    /// How the game ended by the rules, worked out again whenever the board changes
+    rules_outcome: Option<Outcome>,
+    selected_square: Option<Square>,
+    /// A pawn move to the last rank waiting for the player to pick a piece
+    pending_promotion: Option<(Square, Square)>,
+    /// Handed to `ai_worker` while the AI is searching, and back once it stops
+    ai_state: Option<AIState>,
+    ai_worker: Option<AIWorker>,
+    mode: GameMode,
+    /// `None` for games without a time limit
+    clock: Option<ChessClock>,
+    /// Kept here as well as in `ai_state`, which the worker holds while the AI is searching
+    book: Option<BookSettings>,
+    /// Kept here for the same reason as `book`
+    tablebase: Option<Arc<Tablebase>>,
+    /// Kept here for the same reason as `book`
+    difficulty: Difficulty,
+    /// The seed given to `seed_ai`, if any
+    ai_seed: Option<u64>,
+    /// Waiting for `take_events`
+    events: Vec<GameEvent>,
+}
+
+impl Default for ChessGame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChessGame {
+    pub fn new() -> Self {
+        Self::from_position(Position::default(), GameMode::HumanVsAi, None)
+    }
+
+    /// Starts a game from a custom position, e.g. one parsed from FEN,
+    /// with the sides controlled as `mode` says. If the AI is to move, it starts thinking
+    /// on the first `update`, so the AI settings can be changed before that without restarting it.
+    /// With a time control, the clock of the side to move starts running at once.
+    pub fn from_position(
+        start: Position,
+        mode: GameMode,
+        time_control: Option<TimeControl>,
+    ) -> Self {
+        let mut game = Self::idle_at(start, mode);
+        game.clock = time_control.map(ChessClock::new);
+        game.start_clock();
+        game
+    }
+
+    /// A game at `start` where nobody has moved and the AI is not searching yet
+    fn idle_at(start: Position, mode: GameMode) -> Self {
+        Self {
+            board: start.board,
+            start,
+            history: Vec::new(),
+            redo_stack: Vec::new(),
+            rules_outcome: Outcome::of(&[start.board], start.halfmove_clock),
+            selected_square: None,
+            pending_promotion: None,
+            ai_state: Some(AIState::default()),
+            ai_worker: None,
+            mode,
+            clock: None,
+            book: None,
+            tablebase: None,
+            difficulty: Difficulty::default(),
+            ai_seed: None,
+            events: Vec::new(),
+        }
+    }
+
+    /// The current position
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// The position the game started from
+    pub fn start(&self) -> &Position {
+        &self.start
+    }
+
+    /// Who controls each side
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    /// The clocks of both players, if the game is played with a time limit
+    pub fn clock(&self) -> Option<&ChessClock> {
+        self.clock.as_ref()
+    }
+
+    pub fn time_control(&self) -> Option<TimeControl> {
+        self.clock.as_ref().map(ChessClock::time_control)
+    }
+
+    /// Replays a saved game. If it is the AI's turn afterwards, the AI starts thinking on the first `update`.
+    /// Sides the AI played in the saved game are played by the AI again.
+    pub fn from_pgn(pgn: &PgnGame) -> Self {
+        let mode = GameMode::from_sides(
+            pgn.tag("White") != Some(AI_NAME),
+            pgn.tag("Black") != Some(AI_NAME),
+        );
+        let mut game = Self::idle_at(pgn.start, mode);
+        for &chess_move in &pgn.moves {
+            game.play(chess_move);
+        }
+        game
+    }
+
+    /// Exports the game so far, e.g. to save it to a file
+    pub fn to_pgn(&self) -> PgnGame {
+        let white = self.mode.player_name(Color::White);
+        let black = self.mode.player_name(Color::Black);
+        let moves = self.history.iter().map(|entry| entry.chess_move).collect();
+        PgnGame::new(white, black, self.start, moves, self.result())
+    }
+
+    /// Lets the AI play from an opening book in this game, or only search with `None`.
+    /// A search in progress starts over with the new setting on the next `update`.
+    pub fn set_book(&mut self, book: Option<BookSettings>) {
+        self.idle_ai_state().set_book(book.clone());
+        self.book = book;
+    }
+
+    /// The book the AI plays from in this game, if any
+    pub fn book(&self) -> Option<&BookSettings> {
+        self.book.as_ref()
+    }
+
+    /// Lets the AI look endgames up in `tablebase` in this game, or only search with `None`.
+    /// A search in progress starts over with the new setting on the next `update`.
+    pub fn set_tablebase(&mut self, tablebase: Option<Arc<Tablebase>>) {
+        self.idle_ai_state().set_tablebase(tablebase.clone());
+        self.tablebase = tablebase;
+    }
+
+    /// The endgame tables the AI uses in this game, if any
+    pub fn tablebase(&self) -> Option<&Arc<Tablebase>> {
+        self.tablebase.as_ref()
+    }
+
+    /// Makes the AI play at `difficulty` in this game.
+    /// A search in progress starts over with the new setting on the next `update`.
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.idle_ai_state().set_difficulty(difficulty);
+        self.difficulty = difficulty;
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// Makes the AI's random choices from now on follow from `seed`, see `AIState::seed`.
+    /// A search in progress starts over on the next `update`.
+    pub fn seed_ai(&mut self, seed: u64) {
+        self.idle_ai_state().seed(seed);
+        self.ai_seed = Some(seed);
+    }
+
+    pub fn ai_seed(&self) -> Option<u64> {
+        self.ai_seed
+    }
+
+    /// The number of moves played since the initial position,
+    /// counting those before the position the game started from
+    fn ply(&self) -> usize {
+        let before_start = 2 * (self.start.fullmove_number.max(1) - 1)
+            + u32::from(self.start.board.side_to_move() == Color::Black);
+        usize::try_from(before_start).unwrap() + self.history.len()
+    }
+
+    /// Every move played so far, in order
+    pub fn history(&self) -> &[HistoryEntry] {
+        &self.history
+    }
+
+    pub fn selected_square(&self) -> Option<Square> {
+        self.selected_square
+    }
+
+    /// Selects the square, or moves the previously selected piece there if that is legal.
+    /// Only has an effect when a person is to move and the game is not over.
+    /// If the AI plays the other side, it starts searching for its reply.
+    pub fn select(&mut self, square: Option<Square>) {
+        if !self.is_ongoing() {
+            // Nothing can be moved once the game is over
+            return;
+        }
+
+        if !self.is_human_turn() {
+            // The board is about to change under the player
+            return;
+        }
+
+        if self.pending_promotion.is_some() {
+            // The promotion has to be chosen or cancelled first
+            return;
+        }
+
+        if let (Some(original), Some(new_selection)) = (self.selected_square, square) {
+            let possible_moves: Vec<_> = self
+                .moves_from(original)
+                .into_iter()
+                .filter(|chess_move| chess_move.get_dest() == new_selection)
+                .collect();
+            match possible_moves.as_slice() {
+                [] => {}
+                [chess_move] => {
+                    self.play_player_move(*chess_move);
+                    return;
+                }
+                _ => {
+                    // Several moves to the same square only happen when promoting
+                    self.events.push(GameEvent::ChoosePromotion);
+                    self.pending_promotion = Some((original, new_selection));
+                    return;
+                }
+            }
+        }
+
+        if self.selected_square != square {
+            self.selected_square = square;
+            if let Some(square) = square {
+                self.events.push(GameEvent::Selected(square));
+            }
+        }
+    }
+
+    /// The source and destination of a promotion waiting for `promote` to pick the piece
+    pub fn pending_promotion(&self) -> Option<(Square, Square)> {
+        self.pending_promotion
+    }
+
+    /// Finishes the pending promotion with the given piece, or cancels it with `None`.
+    /// Cancelling keeps the pawn selected.
+    pub fn promote(&mut self, piece: Option<Piece>) {
+        let (source, dest) = match self.pending_promotion.take() {
+            Some(promotion) => promotion,
+            None => return,
+        };
+
+        match piece.map(|piece| ChessMove::new(source, dest, Some(piece))) {
+            Some(chess_move) if self.board.legal(chess_move) => self.play_player_move(chess_move),
+            Some(chess_move) => {
+                self.events.push(GameEvent::IllegalPromotion(chess_move));
+                self.pending_promotion = Some((source, dest));
+            }
+            None => self.events.push(GameEvent::PromotionCancelled),
+        }
+    }
+
+    fn play_player_move(&mut self, chess_move: ChessMove) {
+        self.events.push(GameEvent::PlayerMoved(chess_move));
+        self.play(chess_move);
+        self.selected_square = None;
+
+        self.start_ai_if_its_turn();
+    }
+
+    /// Applies the AI's move once its search has finished, starts the AI when it is its turn,
+    /// and ends the game when a player's flag falls
+    pub fn update(&mut self) {
+        if let Some(loser) = self.clock.as_ref().and_then(ChessClock::flagged) {
+            if self.clock.as_ref().and_then(ChessClock::running).is_some() {
+                self.events.push(GameEvent::Flagged(loser));
+                self.cancel_ai();
+                self.selected_square = None;
+                self.pending_promotion = None;
+                self.stop_clock();
+            }
+            return;
+        }
+
+        let ai_move = match &self.ai_worker {
+            Some(worker) => {
+                // Polled first, so that every report sent before the move is picked up too
+                let ai_move = worker.poll();
+                self.events
+                    .extend(worker.progress().map(GameEvent::AiProgress));
+                ai_move
+            }
+            None => None,
+        };
+        if let Some(ai_move) = ai_move {
+            self.events.push(GameEvent::AiMoved(ai_move));
+            self.play(ai_move);
+            if let Some(worker) = self.ai_worker.take() {
+                self.ai_state = Some(worker.stop());
+            }
+        }
+
+        // Starts new games, searches cancelled by a change of settings,
+        // and the reply when the AI plays both sides
+        self.start_ai_if_its_turn();
+    }
+
+    /// Takes back moves until it is a person's turn again,
+    /// so in a game against the AI both the AI's reply and the player's move are undone,
+    /// while between two people (or two AIs) a single move is undone.
+    /// The clocks go back to the time that was left when that position was reached.
+    /// Any search in progress is abandoned.
+    pub fn undo(&mut self) {
+        self.cancel_ai();
+        self.selected_square = None;
+        self.pending_promotion = None;
+
+        if self.history.is_empty() {
+            self.events.push(GameEvent::NothingToUndo);
+        } else {
+            while let Some(entry) = self.history.pop() {
+                self.events.push(GameEvent::TookBack(entry.san.clone()));
+                self.board = entry.before;
+                self.redo_stack.push(entry);
+                if self.is_human_turn() || self.mode == GameMode::AiVsAi {
+                    break;
+                }
+            }
+            self.update_outcome();
+            self.restore_clocks();
+        }
+
+        // Starting positions where the AI moves first can not be undone any further
+        self.start_clock();
+        self.start_ai_if_its_turn();
+    }
+
+    /// Replays the moves taken back by the last `undo`, along with the time they left on the clocks
+    pub fn redo(&mut self) {
+        self.cancel_ai();
+        self.selected_square = None;
+        self.pending_promotion = None;
+
+        if self.redo_stack.is_empty() {
+            self.events.push(GameEvent::NothingToRedo);
+        } else {
+            while let Some(entry) = self.redo_stack.pop() {
+                self.events.push(GameEvent::Replayed(entry.san.clone()));
+                self.board = self.board.make_move_new(entry.chess_move);
+                self.history.push(entry);
+                if self.is_human_turn() || self.mode == GameMode::AiVsAi {
+                    break;
+                }
+            }
+            self.update_outcome();
+            self.restore_clocks();
+        }
+
+        self.start_clock();
+        self.start_ai_if_its_turn();
+    }
+
+    /// Whether the side to move is played by a person rather than the AI
+    fn is_human_turn(&self) -> bool {
+        self.mode.is_human(self.board.side_to_move())
+    }
+
+    /// Everything that happened since the last call, oldest first
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Whether the AI is currently searching for a move
+    pub fn is_thinking(&self) -> bool {
+        self.ai_worker.is_some()
+    }
+
+    fn start_ai_if_its_turn(&mut self) {
+        if self.is_ongoing() && !self.is_human_turn() && self.ai_worker.is_none() {
+            self.events.push(GameEvent::AiThinking);
+            let side = self.board.side_to_move();
+            // With a clock, the AI plays faster the less time it has left
+            let limits = match &self.clock {
+                Some(clock) => SearchLimits::from_clock(
+                    clock.remaining(side),
+                    clock.time_control().time_per_move(),
+                    None,
+                ),
+                None => SearchLimits::move_time(AI_MOVE_TIME),
+            };
+            let ai_state = self
+                .ai_state
+                .take()
+                .expect("The state is only missing while the AI searches");
+            self.ai_worker = Some(AIWorker::spawn(ai_state, self.board, self.ply(), limits));
+        }
+    }
+
+    /// Runs the clock of the side to move, unless the game is over
+    fn start_clock(&mut self) {
+        let ongoing = self.is_ongoing();
+        let side = self.board.side_to_move();
+        match &mut self.clock {
+            Some(clock) if ongoing => clock.start(side),
+            Some(clock) => clock.stop(),
+            None => {}
+        }
+    }
+
+    /// Puts both clocks back to the time that was left when the current position was reached.
+    /// They are left stopped for `start_clock`.
+    fn restore_clocks(&mut self) {
+        if let Some(clock) = &mut self.clock {
+            let times = match self.history.last() {
+                Some(entry) => entry.clocks.unwrap_or_else(|| clock.times()),
+                None => [clock.time_control().base; 2],
+            };
+            clock.set_times(times);
+        }
+    }
+
+    fn stop_clock(&mut self) {
+        if let Some(clock) = &mut self.clock {
+            clock.stop();
+        }
+    }
+
+    /// Stops any AI search in progress
+    pub fn cancel_ai(&mut self) {
+        if let Some(worker) = self.ai_worker.take() {
+            self.events.push(GameEvent::AiCancelled);
+            self.ai_state = Some(worker.stop());
+        }
+    }
+
+    /// The AI's state, after stopping any search that holds it
+    fn idle_ai_state(&mut self) -> &mut AIState {
+        self.cancel_ai();
+        self.ai_state
+            .as_mut()
+            .expect("The state is only missing while the AI searches")
+    }
+
+    /// How the game ended, or `None` while it is still being played
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.rules_outcome.or_else(|| {
+            let loser = self.clock.as_ref()?.flagged()?;
+            Some(Outcome::Timeout { winner: !loser })
+        })
+    }
+
+    /// Works out whether the current position ends the game by the rules,
+    /// which only changes when a move is played or taken back
+    fn update_outcome(&mut self) {
+        // Positions from before the last capture or pawn move can not come back
+        let reversible = self
+            .history
+            .iter()
+            .rposition(|entry| draw::is_irreversible(&entry.before, entry.chess_move));
+        let (first, halfmove_clock) = match reversible {
+            Some(index) => (index + 1, 0),
+            None => (0, self.start.halfmove_clock),
+        };
+
+        let positions: Vec<_> = self.history[first..]
+            .iter()
+            .map(|entry| entry.before)
+            .chain(std::iter::once(self.board))
+            .collect();
+        self.rules_outcome = Outcome::of(&positions, halfmove_clock);
+    }
+
+    pub fn is_ongoing(&self) -> bool {
+        self.outcome().is_none()
+    }
+
+    /// The result in PGN notation: `1-0`, `0-1`, `1/2-1/2`, or `*` while still being played
+    pub fn result(&self) -> &'static str {
+        self.outcome().map_or("*", Outcome::result)
+    }
+
+    /// Lists all legal moves from the given source
+    pub fn moves_from(&self, source: Square) -> Vec<ChessMove> {
+        MoveGen::new_legal(&self.board)
+            .filter(|chess_move| chess_move.get_source() == source)
+            .collect()
+    }
+
+    /// Plays a new move, which makes any undone moves impossible to redo
+    fn play(&mut self, chess_move: ChessMove) {
+        self.redo_stack.clear();
+        let san = san::to_san(&self.board, chess_move);
+        let before = self.board;
+        self.board = self.board.make_move_new(chess_move);
+
+        if let Some(clock) = &mut self.clock {
+            clock.press();
+        }
+        self.history.push(HistoryEntry {
+            chess_move,
+            san,
+            before,
+            clocks: self.clock.as_ref().map(ChessClock::times),
+        });
+        self.update_outcome();
+        if !self.is_ongoing() {
+            self.stop_clock();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(fen: &str, mode: GameMode) -> ChessGame {
+        ChessGame::from_position(Position::from_str(fen).unwrap(), mode, None)
+    }
+
+    /// Plays moves such as `e2e4` for whoever is to move, as a person would with the mouse
+    fn play(game: &mut ChessGame, moves: &[&str]) {
+        for text in moves {
+            let chess_move = ChessMove::from_str(text).unwrap();
+            game.select(Some(chess_move.get_source()));
+            game.select(Some(chess_move.get_dest()));
+            if let Some(piece) = chess_move.get_promotion() {
+                game.promote(Some(piece));
+            }
+            assert_eq!(
+                game.history().last().map(|entry| entry.chess_move),
+                Some(chess_move)
+            );
+        }
+    }
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn checkmate() {
+        let mut game = game(START, GameMode::HumanVsHuman);
+        play(&mut game, &["f2f3", "e7e5", "g2g4"]);
+        assert_eq!(game.outcome(), None);
+        assert_eq!(game.result(), "*");
+
+        play(&mut game, &["d8h4"]);
+        assert_eq!(
+            game.outcome(),
+            Some(Outcome::Checkmate {
+                winner: Color::Black
+            })
+        );
+        assert_eq!(game.result(), "0-1");
+    }
+
+    #[test]
+    fn undo_and_redo_update_the_outcome() {
+        let mut game = game(START, GameMode::HumanVsHuman);
+        play(&mut game, &["f2f3", "e7e5", "g2g4", "d8h4"]);
+        assert!(!game.is_ongoing());
+
+        game.undo();
+        assert_eq!(game.outcome(), None);
+        game.redo();
+        assert_eq!(
+            game.outcome(),
+            Some(Outcome::Checkmate {
+                winner: Color::Black
+            })
+        );
+    }
+
+    #[test]
+    fn stalemate() {
+        let mut game = game("k7/8/8/1Q6/8/8/8/7K w - - 0 1", GameMode::HumanVsHuman);
+        play(&mut game, &["b5b6"]);
+        assert_eq!(game.outcome(), Some(Outcome::Stalemate));
+        assert_eq!(game.result(), "1/2-1/2");
+    }
+
+    #[test]
+    fn fifty_moves_count_from_the_start_position() {
+        let mut game = game("4k3/8/8/8/8/8/8/R3K3 w - - 98 70", GameMode::HumanVsHuman);
+        play(&mut game, &["a1a2"]);
+        assert_eq!(game.outcome(), None);
+        play(&mut game, &["e8d8"]);
+        assert_eq!(game.outcome(), Some(Outcome::Draw(DrawReason::FiftyMoves)));
+    }
+
+    #[test]
+    fn a_capture_resets_the_fifty_move_count() {
+        let mut game = game("4k3/8/8/8/8/8/r7/R3K3 w - - 98 70", GameMode::HumanVsHuman);
+        play(&mut game, &["a1a2", "e8d8"]);
+        assert_eq!(game.outcome(), None);
+    }
+
+    #[test]
+    fn threefold_repetition() {
+        let mut game = game(START, GameMode::HumanVsHuman);
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        play(&mut game, &shuffle);
+        play(&mut game, &shuffle[..3]);
+        assert_eq!(game.outcome(), None);
+        play(&mut game, &shuffle[3..]);
+        assert_eq!(game.outcome(), Some(Outcome::Draw(DrawReason::Repetition)));
+    }
+
+    /// A game where `moves` were played without asking the AI for any of them
+    fn played(mode: GameMode, moves: &[&str]) -> ChessGame {
+        let mut game = ChessGame::idle_at(Position::default(), mode);
+        for text in moves {
+            game.play(ChessMove::from_str(text).unwrap());
+        }
+        game.take_events();
+        game
+    }
+
+    fn moves(game: &ChessGame) -> Vec<String> {
+        game.history()
+            .iter()
+            .map(|entry| entry.chess_move.to_string())
+            .collect()
+    }
+
+    const OPENING: [&str; 4] = ["e2e4", "e7e5", "g1f3", "g8f6"];
+
+    #[test]
+    fn undo_against_the_ai_takes_back_both_moves() {
+        let mut game = played(GameMode::HumanVsAi, &OPENING);
+        game.undo();
+        assert_eq!(moves(&game), ["e2e4", "e7e5"]);
+        assert_eq!(
+            game.board(),
+            played(GameMode::HumanVsAi, &OPENING[..2]).board()
+        );
+        assert!(game.is_human_turn());
+        assert!(!game.is_thinking());
+
+        let took_back: Vec<_> = game
+            .take_events()
+            .into_iter()
+            .filter_map(|event| match event {
+                GameEvent::TookBack(san) => Some(san),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(took_back, ["Nf6", "Nf3"]);
+    }
+
+    #[test]
+    fn undo_between_people_takes_back_one_move() {
+        let mut game = played(GameMode::HumanVsHuman, &OPENING);
+        game.undo();
+        assert_eq!(moves(&game), ["e2e4", "e7e5", "g1f3"]);
+        game.undo();
+        assert_eq!(moves(&game), ["e2e4", "e7e5"]);
+    }
+
+    #[test]
+    fn nothing_to_undo() {
+        let mut game = played(GameMode::HumanVsHuman, &[]);
+        game.undo();
+        assert_eq!(game.board(), &Board::default());
+        assert!(matches!(
+            game.take_events().as_slice(),
+            [GameEvent::NothingToUndo]
+        ));
+    }
+
+    #[test]
+    fn redo_replays_in_order() {
+        let mut game = played(GameMode::HumanVsHuman, &OPENING);
+        let end = *game.board();
+        for _ in 0..3 {
+            game.undo();
+        }
+        assert_eq!(moves(&game), ["e2e4"]);
+
+        game.redo();
+        assert_eq!(moves(&game), ["e2e4", "e7e5"]);
+        game.redo();
+        game.redo();
+        assert_eq!(moves(&game), OPENING);
+        assert_eq!(game.board(), &end);
+
+        game.take_events();
+        game.redo();
+        assert!(matches!(
+            game.take_events().as_slice(),
+            [GameEvent::NothingToRedo]
+        ));
+    }
+
+    #[test]
+    fn redo_against_the_ai_replays_both_moves() {
+        let mut game = played(GameMode::HumanVsAi, &OPENING);
+        game.undo();
+        game.redo();
+        assert_eq!(moves(&game), OPENING);
+        assert!(!game.is_thinking());
+    }
+
+    #[test]
+    fn a_new_move_clears_the_redo_stack() {
+        let mut game = played(GameMode::HumanVsHuman, &OPENING);
+        game.undo();
+        play(&mut game, &["b8c6"]);
+        game.take_events();
+
+        game.redo();
+        assert_eq!(moves(&game), ["e2e4", "e7e5", "g1f3", "b8c6"]);
+        assert!(matches!(
+            game.take_events().as_slice(),
+            [GameEvent::NothingToRedo]
+        ));
+    }
+
+    /// Checks the clocks to within the little time that passes while the test runs
+    fn assert_times(game: &ChessGame, white: u64, black: u64) {
+        let times = game.clock().unwrap().times();
+        for (&time, &expected) in times.iter().zip(&[white, black]) {
+            let expected = Duration::from_secs(expected);
+            assert!(
+                expected - time < Duration::from_millis(500),
+                "{:?} left instead of {:?}",
+                time,
+                expected
+            );
+        }
+    }
+
+    /// Plays `text` after taking `white` and `black` seconds off the clocks
+    fn play_with_times(game: &mut ChessGame, text: &str, white: u64, black: u64) {
+        let clock = game.clock.as_mut().unwrap();
+        clock.set_times([Duration::from_secs(white), Duration::from_secs(black)]);
+        game.start_clock();
+        game.play(ChessMove::from_str(text).unwrap());
+    }
+
+    #[test]
+    fn undo_and_redo_restore_both_clocks() {
+        let mut game = ChessGame::idle_at(Position::default(), GameMode::HumanVsHuman);
+        game.clock = Some(ChessClock::new(TimeControl::increment(1, 2)));
+        game.start_clock();
+
+        play_with_times(&mut game, "e2e4", 50, 60);
+        assert_times(&game, 52, 60);
+        play_with_times(&mut game, "e7e5", 52, 45);
+        assert_times(&game, 52, 47);
+
+        game.undo();
+        assert_times(&game, 52, 60);
+        assert_eq!(game.clock().unwrap().running(), Some(Color::Black));
+        game.undo();
+        assert_times(&game, 60, 60);
+        assert_eq!(game.clock().unwrap().running(), Some(Color::White));
+
+        game.redo();
+        assert_times(&game, 52, 60);
+        game.redo();
+        assert_times(&game, 52, 47);
+        assert_eq!(game.clock().unwrap().running(), Some(Color::White));
+    }
+
+    #[test]
+    fn the_ai_hands_its_state_back() {
+        let mut game = ChessGame::from_position(Position::default(), GameMode::AiVsHuman, None);
+        game.update();
+        assert!(game.is_thinking());
+        assert!(game.ai_state.is_none());
+
+        // Settings can be changed while the AI thinks, which cancels the search
+        game.set_difficulty(Difficulty::Beginner);
+        assert!(!game.is_thinking());
+        assert!(game.ai_state.is_some());
+        assert_eq!(
+            game.ai_state.as_ref().unwrap().difficulty(),
+            Difficulty::Beginner
+        );
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(30);
+        while game.history().is_empty() && std::time::Instant::now() < deadline {
+            game.update();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(game.history().len(), 1);
+        assert!(!game.is_thinking());
+        assert!(game.ai_state.is_some());
+    }
+
+    #[test]
+    fn settings_of_a_new_game_start_the_ai_once() {
+        let mut game = ChessGame::from_position(Position::default(), GameMode::AiVsHuman, None);
+        game.set_tablebase(None);
+        game.set_difficulty(Difficulty::Easy);
+        game.seed_ai(7);
+        assert!(!game.is_thinking());
+
+        game.update();
+        assert!(game.is_thinking());
+        let events = game.take_events();
+        let started = events
+            .iter()
+            .filter(|event| matches!(event, GameEvent::AiThinking))
+            .count();
+        assert_eq!(started, 1);
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, GameEvent::AiCancelled)));
+    }
+}
@@ -0,0 +1,20 @@
+//! The chess game model and engine, usable without SDL.
+//! The SDL front-end in `main.rs` and the headless tools in `src/bin` are built on top of this.
+
+pub mod ai;
+
+pub mod clock;
+
+pub mod draw;
+
+pub mod fen;
+
+pub mod game;
+
+pub mod pgn;
+
+pub mod san;
+
+pub mod selfplay;
+
+pub mod uci;
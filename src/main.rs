@@ -1,24 +1,78 @@
-use sdl2::{event::Event, keyboard::Keycode, mouse::MouseButton, rect::Point};
+use sdl2::{
+    event::{Event, WindowEvent},
+    keyboard::{Keycode, Mod},
+    mouse::MouseButton,
+    rect::Point,
+};
 
-use sdl2::image::InitFlag;
+use sdl2::image::{InitFlag, LoadTexture};
 
-use std::{thread, time::Duration};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+    rc::Rc,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
 
-mod ai;
+use chess::Color;
+
+use sdl_game::{
+    ai::{
+        book::{BookSettings, OpeningBook},
+        tablebase::Tablebase,
+        Difficulty,
+    },
+    clock::{self, TimeControl},
+    fen::Position,
+    game::{ChessGame, GameMode},
+    pgn::PgnGame,
+};
 
 mod board;
-use board::ChessBoard;
+use board::{ChessBoard, GameOverChoice};
 
 mod drawable;
 
+mod move_list;
+
+mod options;
+use options::Options;
+
 mod sdl_handle;
 use sdl_handle::SDLHandle;
 
 mod sprite;
 
+mod text;
+use text::Font;
+
+mod theme;
+use theme::{ThemeManifest, ThemeSprites};
+
 mod utils;
 
-fn initial_board_center(center: (u32, u32)) -> Point {
+const WINDOW_TITLE: &str = "Chess SDL2";
+
+/// The size of the window when it opens, and the smallest it can be resized to
+const WINDOW_SIZE: (u32, u32) = (1024, 768);
+const MIN_WINDOW_SIZE: (u32, u32) = (800, 600);
+
+/// The folder in `assets/` that theme manifests are looked up in, and their extension
+const THEME_FOLDER: &str = "themes";
+const THEME_EXTENSION: &str = "toml";
+
+/// The opening book in `assets/` the AI plays from unless another is given
+const BOOK_NAME: &str = "book.bin";
+
+/// The font in `assets/` used for all text, and its size in points
+const FONT_NAME: &str = "DejaVuSans.ttf";
+const FONT_SIZE: u16 = 14;
+
+/// Where the board is centered, which is the middle of the window
+fn window_center(center: (u32, u32)) -> Point {
     Point::from(utils::map_tuple(center, |val| {
         use std::convert::TryFrom;
         i32::try_from(val).unwrap()
@@ -31,28 +85,296 @@ fn draw_board(sdl_handle: &mut SDLHandle, board: &ChessBoard, board_center: Poin
     sdl_handle.present();
 }
 
+fn load_game(path: &Path) -> Result<ChessGame, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let pgn: PgnGame = text.parse().map_err(|e| format!("{}", e))?;
+    Ok(ChessGame::from_pgn(&pgn))
+}
+
+/// The manifests in the themes folder, sorted by file name
+fn find_themes(folder: &Path) -> Vec<PathBuf> {
+    let mut themes: Vec<PathBuf> = fs::read_dir(folder)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == THEME_EXTENSION))
+                .collect()
+        })
+        .unwrap_or_default();
+    themes.sort();
+    themes
+}
+
+/// The manifest `theme` names: a path to one, or the name of one in the themes folder
+fn theme_path(folder: &Path, theme: &str) -> PathBuf {
+    let path = PathBuf::from(theme);
+    if path.is_file() {
+        path
+    } else {
+        folder.join(theme).with_extension(THEME_EXTENSION)
+    }
+}
+
+/// Switches the board to the theme after `current` in `themes`, going back to the first after the last
+fn next_theme<'a, Loader: LoadTexture>(
+    board: &mut ChessBoard<'a>,
+    loader: &'a Loader,
+    themes: &[PathBuf],
+    current: &mut PathBuf,
+) {
+    let next = themes
+        .iter()
+        .position(|path| path == current)
+        .map_or(0, |index| (index + 1) % themes.len());
+    let path = match themes.get(next) {
+        Some(path) => path,
+        None => return,
+    };
+    match ThemeManifest::load(path)
+        .and_then(|manifest| Ok((ThemeSprites::load(loader, &manifest)?, manifest)))
+    {
+        Ok((sprites, manifest)) => {
+            println!("Switched to the {} theme", manifest.name);
+            board.set_theme(manifest.colors, sprites);
+            *current = path.clone();
+        }
+        Err(e) => eprintln!("Could not switch theme: {}", e),
+    }
+}
+
+/// Starts a new game from the FEN on the clipboard
+fn paste_game(
+    sdl_handle: &SDLHandle,
+    mode: GameMode,
+    time_control: Option<TimeControl>,
+) -> Result<ChessGame, String> {
+    let text = sdl_handle.clipboard_text()?;
+    let fen = text.trim();
+    let position: Position = fen
+        .parse()
+        .map_err(|e| format!("Invalid FEN \"{}\": {}", fen, e))?;
+    Ok(ChessGame::from_position(position, mode, time_control))
+}
+
+/// The game mode Ctrl plus `key` starts a new game in
+fn mode_key(key: Keycode) -> Option<GameMode> {
+    match key {
+        Keycode::Num1 => Some(GameMode::HumanVsAi),
+        Keycode::Num2 => Some(GameMode::AiVsHuman),
+        Keycode::Num3 => Some(GameMode::HumanVsHuman),
+        Keycode::Num4 => Some(GameMode::AiVsAi),
+        _ => None,
+    }
+}
+
+/// Starts a new game in `mode`, with the same time control, book and difficulty as the current game
+fn new_game(board: &mut ChessBoard, mode: GameMode) {
+    println!("New {} game", mode);
+    let time_control = board.game().time_control();
+    replace_game(
+        board,
+        ChessGame::from_position(Position::default(), mode, time_control),
+    );
+}
+
+/// Replaces the game on the board, keeping the book, tablebase, difficulty and seed the current game uses
+fn replace_game(board: &mut ChessBoard, mut game: ChessGame) {
+    game.set_book(board.game().book().cloned());
+    game.set_tablebase(board.game().tablebase().cloned());
+    game.set_difficulty(board.game().difficulty());
+    if let Some(seed) = board.game().ai_seed() {
+        game.seed_ai(seed);
+    }
+    board.set_game(game);
+}
+
+/// Turns the book on or off for the current game
+fn toggle_book(game: &mut ChessGame, book: Option<&BookSettings>) {
+    if game.book().is_some() {
+        println!("The AI no longer plays from the book in this game");
+        game.set_book(None);
+    } else if let Some(book) = book {
+        println!("The AI plays from the book in this game");
+        game.set_book(Some(book.clone()));
+    } else {
+        eprintln!("There is no opening book to play from");
+    }
+}
+
+/// Makes the AI of the current game play at the next difficulty
+fn next_difficulty(game: &mut ChessGame) {
+    let difficulty = game.difficulty().next();
+    println!("The AI plays at {} difficulty", difficulty);
+    game.set_difficulty(difficulty);
+}
+
+/// The window title, with the AI's difficulty when it is held back,
+/// the time left on each clock and the result once the game is over
+fn window_title(game: &ChessGame) -> String {
+    let mut title = WINDOW_TITLE.to_string();
+    if game.difficulty() != Difficulty::Full {
+        title += &format!(" - AI: {}", game.difficulty());
+    }
+    if let Some(clock) = game.clock() {
+        title += &format!(
+            " - White {} | Black {}",
+            clock::format_time(clock.remaining(Color::White)),
+            clock::format_time(clock.remaining(Color::Black))
+        );
+    }
+    if let Some(outcome) = game.outcome() {
+        title += &format!(" - {}", outcome);
+    }
+    title
+}
+
+/// Stops the AI before the window closes. The game is only saved with Ctrl+S,
+/// so that quitting never overwrites a game saved earlier.
+fn shut_down(game: &mut ChessGame) {
+    game.cancel_ai();
+}
+
+fn save_game(game: &ChessGame, path: &Path) {
+    match fs::write(path, game.to_pgn().to_string()) {
+        Ok(()) => println!("Saved game to {}", path.display()),
+        Err(e) => eprintln!("Could not save game to {}: {}", path.display(), e),
+    }
+}
+
 fn main() {
+    let options = Options::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}\n{}", e, options::USAGE);
+        process::exit(2);
+    });
+
+    let mut game = match &options.pgn {
+        Some(path) => load_game(path).unwrap_or_else(|e| {
+            eprintln!("Could not load {}: {}", path.display(), e);
+            process::exit(1);
+        }),
+        None => ChessGame::from_position(
+            options.fen.unwrap_or_default(),
+            options.mode,
+            options.time_control,
+        ),
+    };
+    println!("Starting from {}", game.start());
+
     println!("Hello, world!");
 
-    let mut sdl_handle = SDLHandle::init("Chess SDL2", (800, 600), InitFlag::PNG).unwrap();
+    let mut sdl_handle =
+        SDLHandle::init(WINDOW_TITLE, WINDOW_SIZE, MIN_WINDOW_SIZE, InitFlag::PNG).unwrap();
+
+    let book_path = options
+        .book
+        .clone()
+        .unwrap_or_else(|| sdl_handle.asset_path(BOOK_NAME));
+    // The game is still playable without a book, so a missing one is not fatal
+    let book = match OpeningBook::load(&book_path) {
+        Ok(book) => {
+            println!(
+                "Loaded {} book moves from {}",
+                book.len(),
+                book_path.display()
+            );
+            Some(BookSettings {
+                max_plies: options.book_plies,
+                ..BookSettings::new(book)
+            })
+        }
+        Err(e) => {
+            eprintln!("Could not load the opening book: {}", e);
+            None
+        }
+    };
+    if options.use_book {
+        game.set_book(book.clone());
+    }
+
+    game.set_difficulty(options.difficulty);
+    if let Some(seed) = options.seed {
+        game.seed_ai(seed);
+    }
+
+    if let Some(path) = &options.syzygy {
+        // Like the book, the tables only make the AI stronger, so the game goes on without them
+        match Tablebase::open(path) {
+            Ok(tablebase) => {
+                println!(
+                    "Found {} tables of up to {} pieces in {}",
+                    tablebase.len(),
+                    tablebase.max_pieces(),
+                    path.display()
+                );
+                game.set_tablebase(Some(Arc::new(tablebase)));
+            }
+            Err(e) => eprintln!("Could not open the tablebases: {}", e),
+        }
+    }
+
     let mut events = sdl_handle.event_pump().unwrap();
-    let mut board_center = initial_board_center(sdl_handle.center_of_draw());
+    let mut board_center = window_center(sdl_handle.center_of_draw());
 
     {
-        let sprite_sheet_path = sdl_handle.asset_path("sprite_sheet.png");
         let texture_creator = sdl_handle.texture_creator();
-        let sprites =
-            sprite::load_grid_sprite_sheet(&texture_creator, sprite_sheet_path, 32).unwrap();
-        let mut board = ChessBoard::new(sprites);
+        let theme_folder = sdl_handle.asset_path(THEME_FOLDER);
+        let themes = find_themes(&theme_folder);
+        let mut theme = theme_path(&theme_folder, &options.theme);
+        let (manifest, sprites) = ThemeManifest::load(&theme)
+            .and_then(|manifest| {
+                let sprites = ThemeSprites::load(&texture_creator, &manifest)?;
+                Ok((manifest, sprites))
+            })
+            .unwrap_or_else(|e| {
+                eprintln!("Could not load theme: {}", e);
+                process::exit(1);
+            });
+        let ttf_context = sdl_handle.ttf_context();
+        let font = Rc::new(
+            Font::load(
+                &ttf_context,
+                &texture_creator,
+                sdl_handle.asset_path(FONT_NAME),
+                FONT_SIZE,
+            )
+            .unwrap(),
+        );
+        let mut board = ChessBoard::new(sprites, font, manifest.colors, game);
+        board.fit(sdl_handle.draw_size());
+        // The outcome last announced, so it is only announced once
+        let mut shown_outcome = None;
+        // Only changed when it has to, since the clocks would otherwise update it every frame
+        let mut shown_title = String::new();
 
         'run_loop: loop {
             while let Some(event) = events.poll_event() {
                 match event {
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Escape),
+                        ..
+                    }
+                    | Event::MouseButtonDown {
+                        mouse_btn: MouseButton::Right,
+                        ..
+                    } if board.game().pending_promotion().is_some() => {
+                        board.game_mut().promote(None)
+                    }
                     Event::Quit { .. }
                     | Event::KeyDown {
                         keycode: Some(Keycode::Escape),
                         ..
-                    } => break 'run_loop,
+                    } => {
+                        shut_down(board.game_mut());
+                        break 'run_loop;
+                    }
+                    // Also sent when going fullscreen, unlike `WindowEvent::Resized`
+                    Event::Window {
+                        win_event: WindowEvent::SizeChanged(..),
+                        ..
+                    } => {
+                        board_center = window_center(sdl_handle.center_of_draw());
+                        board.fit(sdl_handle.draw_size());
+                    }
                     Event::MouseButtonDown {
                         mouse_btn: MouseButton::Left,
                         x,
@@ -60,31 +382,156 @@ fn main() {
                         ..
                     } => {
                         let in_board = Point::new(x - board_center.x(), board_center.y() - y);
-                        board.select(ChessBoard::tile_coord(in_board));
+                        if board.click_move_list(in_board) {
+                            // Jumped to a move, or clicked the empty part of the list
+                        } else if board.is_reviewing() {
+                            // The board is read-only while reviewing, so a click returns to the game
+                            board.stop_review();
+                        } else if !board.game().is_ongoing() {
+                            match board.game_over_choice(in_board) {
+                                Some(GameOverChoice::NewGame) => {
+                                    let mode = board.game().mode();
+                                    new_game(&mut board, mode)
+                                }
+                                Some(GameOverChoice::Quit) => {
+                                    shut_down(board.game_mut());
+                                    break 'run_loop;
+                                }
+                                None => {}
+                            }
+                        } else if board.game().pending_promotion().is_some() {
+                            // Clicking outside the picker cancels the promotion
+                            let choice = board.promotion_choice(in_board);
+                            board.game_mut().promote(choice);
+                        } else {
+                            board.press(in_board);
+                        }
+                    }
+                    Event::MouseMotion { x, y, .. } => {
+                        board.drag_to(Point::new(x - board_center.x(), board_center.y() - y))
+                    }
+                    Event::MouseButtonUp {
+                        mouse_btn: MouseButton::Left,
+                        x,
+                        y,
+                        ..
+                    } => board.release(Point::new(x - board_center.x(), board_center.y() - y)),
+                    Event::MouseWheel { y, .. } => board.scroll_moves(-y),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::S),
+                        keymod,
+                        ..
+                    } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                        save_game(board.game(), &options.save)
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::O),
+                        keymod,
+                        ..
+                    } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                        match load_game(&options.save) {
+                            Ok(game) => replace_game(&mut board, game),
+                            Err(e) => {
+                                eprintln!("Could not load {}: {}", options.save.display(), e)
+                            }
+                        }
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Z),
+                        keymod,
+                        ..
+                    } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                        board.game_mut().undo()
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Y),
+                        keymod,
+                        ..
+                    } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                        board.game_mut().redo()
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::V),
+                        keymod,
+                        ..
+                    } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                        match paste_game(
+                            &sdl_handle,
+                            board.game().mode(),
+                            board.game().time_control(),
+                        ) {
+                            Ok(game) => {
+                                println!("Starting from {}", game.start());
+                                replace_game(&mut board, game);
+                            }
+                            Err(e) => eprintln!("Could not start from the clipboard: {}", e),
+                        }
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::N),
+                        keymod,
+                        ..
+                    } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                        let mode = board.game().mode();
+                        new_game(&mut board, mode)
+                    }
+                    Event::KeyDown {
+                        keycode: Some(key),
+                        keymod,
+                        ..
+                    } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD)
+                        && mode_key(key).is_some() =>
+                    {
+                        new_game(&mut board, mode_key(key).unwrap())
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Return),
+                        ..
+                    } if !board.game().is_ongoing() => {
+                        let mode = board.game().mode();
+                        new_game(&mut board, mode)
                     }
                     Event::KeyDown {
                         keycode: Some(key), ..
                     } => match key {
-                        Keycode::Left => board_center = board_center.offset(-5, 0),
-                        Keycode::Right => board_center = board_center.offset(5, 0),
-                        Keycode::Up => board_center = board_center.offset(0, -5),
-                        Keycode::Down => board_center = board_center.offset(0, 5),
+                        Keycode::F => board.flip(),
+                        Keycode::B => toggle_book(board.game_mut(), book.as_ref()),
+                        Keycode::D => next_difficulty(board.game_mut()),
+                        Keycode::T => next_theme(&mut board, &texture_creator, &themes, &mut theme),
+                        Keycode::F11 => {
+                            if let Err(e) = sdl_handle.toggle_fullscreen() {
+                                eprintln!("Could not toggle fullscreen: {}", e);
+                            }
+                        }
                         _ => {}
                     },
                     _ => {}
                 }
             }
 
-            draw_board(&mut sdl_handle, &board, board_center);
+            board.game_mut().update();
+            for event in board.game_mut().take_events() {
+                println!("{}", event);
+            }
+
+            let outcome = board.game().outcome();
+            if outcome != shown_outcome {
+                if let Some(outcome) = outcome {
+                    println!("{}", outcome);
+                }
+                shown_outcome = outcome;
+            }
 
-            if !board.is_ongoing() {
-                if board.is_player_winner() {
-                    println!("Player won");
-                } else {
-                    println!("Stalemate or AI won");
+            let title = window_title(board.game());
+            if title != shown_title {
+                if let Err(e) = sdl_handle.set_title(&title) {
+                    eprintln!("Could not set the window title: {}", e);
                 }
+                shown_title = title;
             }
 
+            draw_board(&mut sdl_handle, &board, board_center);
+
             thread::sleep(Duration::new(0, 1_000_000_000 / 60));
         }
     }
@@ -0,0 +1,185 @@
+use sdl2::rect::{Point, Rect};
+
+use std::convert::TryFrom;
+
+use sdl_game::game::ChessGame;
+
+use crate::{
+    drawable::{Drawable, Renderer},
+    text::{Font, Label},
+    theme::Theme,
+};
+
+/// Space around the rows and between the text and the highlight, in pixels
+const PADDING: i32 = 4;
+
+/// Where each column starts, relative to the left of the panel
+const NUMBER_COLUMN: i32 = PADDING;
+const WHITE_COLUMN: i32 = 40;
+const BLACK_COLUMN: i32 = 105;
+
+/// One line of the list: a move number with White's and Black's move,
+/// given as indices into the game's history
+struct Row {
+    number: usize,
+    plies: [Option<usize>; 2],
+}
+
+/// The moves of a game in numbered pairs, e.g. `1. e4 e5 2. Nf3`
+#[derive(Default)]
+pub struct MoveList {
+    /// The first row shown, or `None` to keep the last move in view
+    scroll: Option<usize>,
+}
+
+impl MoveList {
+    /// Scrolls by `rows`, downwards for positive values.
+    /// Scrolling to the end follows new moves again.
+    pub fn scroll(&mut self, rows: i32, game: &ChessGame, font: &Font, area: Rect) {
+        let last_top = Self::last_top_row(game, font, area);
+        let top = i64::try_from(self.top_row(game, font, area)).unwrap() + i64::from(rows);
+        let top = usize::try_from(top.max(0)).unwrap();
+        self.scroll = if top >= last_top { None } else { Some(top) };
+    }
+
+    /// Goes back to following the last move
+    pub fn scroll_to_end(&mut self) {
+        self.scroll = None;
+    }
+
+    /// The index into the history of the move shown at `p`, if any
+    pub fn ply_at(&self, game: &ChessGame, font: &Font, area: Rect, p: Point) -> Option<usize> {
+        if !area.contains_point(p) {
+            return None;
+        }
+
+        let row_height = Self::row_height(font);
+        let row = usize::try_from((p.y() - area.top() - PADDING) / row_height).ok()?;
+        let column = if p.x() - area.left() < BLACK_COLUMN {
+            0
+        } else {
+            1
+        };
+        Self::rows(game)
+            .get(self.top_row(game, font, area) + row)?
+            .plies[column]
+    }
+
+    fn row_height(font: &Font) -> i32 {
+        i32::try_from(font.height()).unwrap() + PADDING
+    }
+
+    fn visible_rows(font: &Font, area: Rect) -> usize {
+        let height = i32::try_from(area.height()).unwrap() - 2 * PADDING;
+        usize::try_from(height / Self::row_height(font)).unwrap_or(0)
+    }
+
+    /// The top row when scrolled all the way down
+    fn last_top_row(game: &ChessGame, font: &Font, area: Rect) -> usize {
+        Self::rows(game)
+            .len()
+            .saturating_sub(Self::visible_rows(font, area))
+    }
+
+    fn top_row(&self, game: &ChessGame, font: &Font, area: Rect) -> usize {
+        let last_top = Self::last_top_row(game, font, area);
+        self.scroll.map_or(last_top, |top| top.min(last_top))
+    }
+
+    /// Pairs up the moves, starting on Black's side when the game started with Black to move
+    fn rows(game: &ChessGame) -> Vec<Row> {
+        let start = game.start();
+        let first_side = match start.board.side_to_move() {
+            chess::Color::White => 0,
+            chess::Color::Black => 1,
+        };
+        let first_number = usize::try_from(start.fullmove_number).unwrap();
+
+        let mut rows: Vec<Row> = Vec::new();
+        for ply in 0..game.history().len() {
+            let side = (first_side + ply) % 2;
+            let number = first_number + (first_side + ply) / 2;
+            match rows.last_mut() {
+                Some(row) if row.number == number => row.plies[side] = Some(ply),
+                _ => {
+                    let mut plies = [None; 2];
+                    plies[side] = Some(ply);
+                    rows.push(Row { number, plies });
+                }
+            }
+        }
+        rows
+    }
+}
+
+/// A `MoveList` drawn for a game, with `current` (an index into the history) highlighted
+pub struct MoveListView<'v, 'a> {
+    pub list: &'v MoveList,
+    pub game: &'v ChessGame,
+    pub font: &'v Font<'a>,
+    pub theme: &'v Theme,
+    pub current: Option<usize>,
+}
+
+impl Drawable for MoveListView<'_, '_> {
+    fn draw_on(&self, dest: &mut Renderer, area: Rect) -> Result<(), String> {
+        dest.set_draw_color(self.theme.panel_background);
+        dest.fill_rect(area)?;
+        dest.set_draw_color(self.theme.text);
+        dest.draw_rect(area)?;
+
+        let history = self.game.history();
+        let row_height = MoveList::row_height(self.font);
+        let rows = MoveList::rows(self.game);
+        let top = self.list.top_row(self.game, self.font, area);
+        let visible = MoveList::visible_rows(self.font, area);
+
+        for (index, row) in rows.iter().skip(top).take(visible).enumerate() {
+            let y = area.top() + PADDING + i32::try_from(index).unwrap() * row_height;
+            let cell = |column: i32, width: i32| {
+                Rect::new(
+                    area.left() + column,
+                    y,
+                    u32::try_from(width).unwrap(),
+                    u32::try_from(row_height).unwrap(),
+                )
+            };
+
+            let number = match row.plies[0] {
+                Some(_) => format!("{}.", row.number),
+                None => format!("{}...", row.number),
+            };
+            Label::new(self.font, number, self.theme.text)
+                .draw_on(dest, cell(NUMBER_COLUMN, WHITE_COLUMN - NUMBER_COLUMN))?;
+
+            let columns = [
+                (WHITE_COLUMN, BLACK_COLUMN - WHITE_COLUMN),
+                (
+                    BLACK_COLUMN,
+                    i32::try_from(area.width()).unwrap() - BLACK_COLUMN - PADDING,
+                ),
+            ];
+            for (&ply, &(column, width)) in row.plies.iter().zip(columns.iter()) {
+                let ply = match ply {
+                    Some(ply) => ply,
+                    None => continue,
+                };
+                let rect = cell(column, width);
+                if Some(ply) == self.current {
+                    dest.set_draw_color(self.theme.current_move);
+                    dest.fill_rect(rect)?;
+                }
+                let text_area = Rect::new(
+                    rect.left() + PADDING,
+                    rect.top(),
+                    rect.width(),
+                    rect.height(),
+                );
+                Label::new(self.font, history[ply].san.as_str(), self.theme.text)
+                    .draw_on(dest, text_area)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use std::path::PathBuf;
+
+use sdl_game::{
+    ai::{book, Difficulty},
+    clock::TimeControl,
+    fen::Position,
+    game::GameMode,
+};
+
+pub const USAGE: &str =
+    "Usage: sdl_game [--pgn <file> | --fen <fen>] [--mode <mode> | --side <white|black>]
+                [--time <control>] [--book <file> | --no-book] [--book-plies <n>]
+                [--syzygy <dir>] [--difficulty <level>] [--seed <n>]
+                [--theme <name|file>] [--save <file>]
+
+Options:
+    --pgn <file>     Continue the game saved in a PGN file
+    --fen <fen>      Start a new game from the position in FEN, e.g.
+                     --fen \"8/8/8/4k3/8/8/4P3/4K3 w - - 0 1\"
+    --mode <mode>    Who plays White and Black: human-ai, ai-human, human-human
+                     or ai-ai [default: human-ai]
+    --side <side>    Which side to play against the AI, the same as
+                     --mode human-ai (white) or --mode ai-human (black)
+    --time <control> Play with clocks: minutes plus seconds of increment (5+3)
+                     or delay (5d3) per move, or blitz (5+3), rapid (15+10)
+                     or classical (90+30) [default: no clocks]
+    --book <file>    The opening book (Polyglot .bin) the AI plays from
+                     [default: assets/book.bin]
+    --no-book        Let the AI search from the first move; B turns the book
+                     back on during a game
+    --book-plies <n> How many moves into the game the book is used [default: 16]
+    --syzygy <dir>   Where the Syzygy endgame tables (.rtbw and .rtbz) are
+                     [default: none]
+    --difficulty <level>
+                     How strongly the AI plays: beginner, easy, medium, hard
+                     or full [default: full]; D switches it during a game
+    --seed <n>       Makes the AI's random choices repeatable; every new game
+                     starts from the same seed
+    --theme <theme>  The colors and pieces to draw the board with: the name of
+                     a manifest in assets/themes, or the path to one
+                     [default: classic]
+    --save <file>    Where Ctrl+S saves the game [default: game.pgn]";
+
+/// Settings given on the command line
+pub struct Options {
+    /// A saved game to continue
+    pub pgn: Option<PathBuf>,
+    /// A position to start a new game from
+    pub fen: Option<Position>,
+    /// Who controls each side in a new game
+    pub mode: GameMode,
+    /// The clocks to play a new game with, if any
+    pub time_control: Option<TimeControl>,
+    /// The opening book to use instead of the bundled one
+    pub book: Option<PathBuf>,
+    /// Whether the AI starts out playing from the book
+    pub use_book: bool,
+    /// The number of moves into a game the book is used for
+    pub book_plies: usize,
+    /// The folder of endgame tables, if any
+    pub syzygy: Option<PathBuf>,
+    pub difficulty: Difficulty,
+    /// Seeds the AI at the start of every game
+    pub seed: Option<u64>,
+    /// The name of a theme in the themes folder, or the path to a theme manifest
+    pub theme: String,
+    /// Where to save the game
+    pub save: PathBuf,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            pgn: None,
+            fen: None,
+            mode: GameMode::HumanVsAi,
+            time_control: None,
+            book: None,
+            use_book: true,
+            book_plies: book::DEFAULT_MAX_PLIES,
+            syzygy: None,
+            difficulty: Difficulty::default(),
+            seed: None,
+            theme: "classic".to_string(),
+            save: PathBuf::from("game.pgn"),
+        }
+    }
+}
+
+impl Options {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut options = Self::default();
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("Missing value for {}", arg))
+            };
+            match arg.as_str() {
+                "--pgn" => options.pgn = Some(value()?.into()),
+                "--fen" => {
+                    let fen = value()?;
+                    let position = fen
+                        .parse()
+                        .map_err(|e| format!("Invalid FEN \"{}\": {}", fen, e))?;
+                    options.fen = Some(position);
+                }
+                "--mode" => options.mode = value()?.parse()?,
+                "--side" => {
+                    options.mode = match value()?.to_lowercase().as_str() {
+                        "white" | "w" => GameMode::HumanVsAi,
+                        "black" | "b" => GameMode::AiVsHuman,
+                        side => return Err(format!("Unknown side {}", side)),
+                    }
+                }
+                "--time" => options.time_control = Some(value()?.parse()?),
+                "--book" => options.book = Some(value()?.into()),
+                "--no-book" => options.use_book = false,
+                "--book-plies" => {
+                    let plies = value()?;
+                    options.book_plies = plies
+                        .parse()
+                        .map_err(|e| format!("Invalid number of plies {}: {}", plies, e))?;
+                }
+                "--syzygy" => options.syzygy = Some(value()?.into()),
+                "--difficulty" => options.difficulty = value()?.parse()?,
+                "--seed" => {
+                    let seed = value()?;
+                    options.seed = Some(
+                        seed.parse()
+                            .map_err(|e| format!("Invalid seed {}: {}", seed, e))?,
+                    );
+                }
+                "--theme" => options.theme = value()?,
+                "--save" => options.save = value()?.into(),
+                _ => return Err(format!("Unknown argument {}", arg)),
+            }
+        }
+
+        if options.pgn.is_some() && options.fen.is_some() {
+            return Err("--pgn and --fen cannot be used together".to_string());
+        }
+
+        Ok(options)
+    }
+}
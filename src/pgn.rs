@@ -0,0 +1,502 @@
+//! Reading and writing games in Portable Game Notation
+
+use chess::{Board, ChessMove};
+
+use std::{
+    fmt,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{fen::Position, san};
+
+/// Moves written per line, to keep lines under the 80 columns the standard asks for
+const MOVES_PER_LINE: usize = 8;
+
+/// A single game as stored in PGN
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgnGame {
+    /// Tag pairs in the order they should be written
+    pub tags: Vec<(String, String)>,
+    /// The position before the first move, including its move number
+    pub start: Position,
+    pub moves: Vec<ChessMove>,
+    /// A comment after the last move, e.g. saying how the game ended
+    pub comment: Option<String>,
+    /// One of `1-0`, `0-1`, `1/2-1/2` or `*`
+    pub result: String,
+}
+
+impl PgnGame {
+    /// Creates a game with the seven standard tags filled in.
+    /// A `FEN` tag is added when the game does not start from the initial position.
+    pub fn new(
+        white: &str,
+        black: &str,
+        start: Position,
+        moves: Vec<ChessMove>,
+        result: &str,
+    ) -> Self {
+        let mut tags = vec![
+            ("Event".to_string(), "Casual game".to_string()),
+            ("Site".to_string(), "?".to_string()),
+            ("Date".to_string(), today()),
+            ("Round".to_string(), "-".to_string()),
+            ("White".to_string(), white.to_string()),
+            ("Black".to_string(), black.to_string()),
+            ("Result".to_string(), result.to_string()),
+        ];
+
+        if start != Position::default() {
+            tags.push(("SetUp".to_string(), "1".to_string()));
+            tags.push(("FEN".to_string(), start.to_string()));
+        }
+
+        Self {
+            tags,
+            start,
+            moves,
+            comment: None,
+            result: result.to_string(),
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Every position of the game, from the start to after the last move
+    pub fn positions(&self) -> impl Iterator<Item = Board> + '_ {
+        std::iter::once(self.start.board).chain(self.moves.iter().scan(
+            self.start.board,
+            |board, &chess_move| {
+                *board = board.make_move_new(chess_move);
+                Some(*board)
+            },
+        ))
+    }
+}
+
+impl fmt::Display for PgnGame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, value) in &self.tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(f, "[{} \"{}\"]", name, value)?;
+        }
+        writeln!(f)?;
+
+        let mut board = self.start.board;
+        // Custom positions can start on any move number, with either side to move
+        let first_ply = 2 * (self.start.fullmove_number as usize - 1)
+            + match board.side_to_move() {
+                chess::Color::White => 0,
+                chess::Color::Black => 1,
+            };
+
+        for (index, &chess_move) in self.moves.iter().enumerate() {
+            let ply = first_ply + index;
+            if index > 0 {
+                let separator = if index % MOVES_PER_LINE == 0 {
+                    "\n"
+                } else {
+                    " "
+                };
+                write!(f, "{}", separator)?;
+            }
+
+            if ply % 2 == 0 {
+                write!(f, "{}. ", ply / 2 + 1)?;
+            } else if index == 0 {
+                write!(f, "{}... ", ply / 2 + 1)?;
+            }
+
+            write!(f, "{}", san::to_san(&board, chess_move))?;
+            board = board.make_move_new(chess_move);
+        }
+
+        if !self.moves.is_empty() {
+            write!(f, " ")?;
+        }
+        if let Some(comment) = &self.comment {
+            // Braces cannot be escaped inside a comment
+            write!(f, "{{{}}} ", comment.replace('}', ")"))?;
+        }
+        writeln!(f, "{}", self.result)
+    }
+}
+
+/// A problem found while reading PGN, with the position of the offending text
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgnError {
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column number, in characters
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl FromStr for PgnGame {
+    type Err = PgnError;
+
+    /// Reads the first game in `text`
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Parser::new(text).parse_game()
+    }
+}
+
+/// Reads PGN one character at a time, keeping track of where it is for error messages
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn error<T>(&self, line: usize, column: usize, message: String) -> Result<T, PgnError> {
+        Err(PgnError {
+            line,
+            column,
+            message,
+        })
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.next();
+        }
+    }
+
+    /// Reads characters up to (but not including) the next delimiter
+    fn read_token(&mut self) -> String {
+        let mut token = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || matches!(c, '[' | ']' | '{' | '}' | '(' | ')' | ';' | '"') {
+                break;
+            }
+            token.push(c);
+            self.next();
+        }
+        token
+    }
+
+    fn parse_game(&mut self) -> Result<PgnGame, PgnError> {
+        let mut tags = Vec::new();
+        let mut start = Position::default();
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('[') {
+                break;
+            }
+
+            let (line, column) = (self.line, self.column);
+            let (name, value) = self.parse_tag()?;
+            if name == "FEN" {
+                start = match value.parse() {
+                    Ok(position) => position,
+                    Err(e) => return self.error(line, column, format!("Invalid FEN tag: {}", e)),
+                };
+            }
+            tags.push((name, value));
+        }
+
+        let mut board = start.board;
+        let mut moves = Vec::new();
+        let mut variation_depth = 0;
+        // The latest comment, while no move has followed it
+        let mut comment = None;
+
+        loop {
+            self.skip_whitespace();
+            let (line, column) = (self.line, self.column);
+            let c = match self.peek() {
+                Some(c) => c,
+                None if variation_depth > 0 => {
+                    return self.error(line, column, "Unterminated variation".to_string())
+                }
+                None => return self.error(line, column, "Missing game result".to_string()),
+            };
+
+            match c {
+                '{' => {
+                    self.next();
+                    let mut text = String::new();
+                    loop {
+                        match self.next() {
+                            Some('}') => break,
+                            Some(c) => text.push(c),
+                            None => {
+                                return self.error(line, column, "Unterminated comment".to_string())
+                            }
+                        }
+                    }
+                    if variation_depth == 0 {
+                        comment = Some(text.trim().to_string());
+                    }
+                }
+                ';' => while !matches!(self.next(), Some('\n') | None) {},
+                '(' => {
+                    // Variations are not part of the game itself
+                    self.next();
+                    variation_depth += 1;
+                }
+                ')' => {
+                    if variation_depth == 0 {
+                        return self.error(line, column, "Unexpected ')'".to_string());
+                    }
+                    self.next();
+                    variation_depth -= 1;
+                }
+                '[' | ']' | '}' | '"' => {
+                    return self.error(line, column, format!("Unexpected '{}'", c));
+                }
+                _ => {
+                    let token = self.read_token();
+                    if variation_depth > 0 || token.starts_with('$') {
+                        // Skip moves inside variations and numeric annotation glyphs
+                        continue;
+                    }
+
+                    if matches!(token.as_str(), "1-0" | "0-1" | "1/2-1/2" | "*") {
+                        return Ok(PgnGame {
+                            tags,
+                            start,
+                            moves,
+                            comment,
+                            result: token,
+                        });
+                    }
+
+                    // Move numbers like `12.` or `12...`, possibly glued to the move
+                    let san = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                    if san.is_empty() {
+                        continue;
+                    }
+
+                    match san::from_san(&board, san) {
+                        Some(chess_move) => {
+                            board = board.make_move_new(chess_move);
+                            moves.push(chess_move);
+                            comment = None;
+                        }
+                        None => {
+                            return self.error(
+                                line,
+                                column,
+                                format!("Illegal or unknown move '{}' in position {}", san, board),
+                            )
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads `[Name "value"]`
+    fn parse_tag(&mut self) -> Result<(String, String), PgnError> {
+        let (line, column) = (self.line, self.column);
+        self.next();
+        self.skip_whitespace();
+
+        let name = self.read_token();
+        if name.is_empty() {
+            return self.error(self.line, self.column, "Missing tag name".to_string());
+        }
+
+        self.skip_whitespace();
+        if self.peek() != Some('"') {
+            return self.error(
+                self.line,
+                self.column,
+                format!("Expected '\"' to start the value of tag {}", name),
+            );
+        }
+        self.next();
+
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                Some('"') => break,
+                Some('\\') => match self.next() {
+                    Some(escaped) => value.push(escaped),
+                    None => break,
+                },
+                Some('\n') | None => {
+                    return self.error(line, column, format!("Unterminated value of tag {}", name))
+                }
+                Some(c) => value.push(c),
+            }
+        }
+
+        self.skip_whitespace();
+        if self.next() != Some(']') {
+            return self.error(
+                self.line,
+                self.column,
+                format!("Expected ']' to close tag {}", name),
+            );
+        }
+
+        Ok((name, value))
+    }
+}
+
+/// Today's date (UTC) in the `YYYY.MM.DD` format PGN uses
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() / 86_400)
+        .unwrap_or(0) as i64;
+
+    // Converts days since 1970-01-01 to a civil date; see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moves(board: Board, sans: &[&str]) -> Vec<ChessMove> {
+        let mut board = board;
+        sans.iter()
+            .map(|text| {
+                let chess_move = san::from_san(&board, text).unwrap();
+                board = board.make_move_new(chess_move);
+                chess_move
+            })
+            .collect()
+    }
+
+    fn error_at(text: &str) -> (usize, usize) {
+        let error = text.parse::<PgnGame>().unwrap_err();
+        (error.line, error.column)
+    }
+
+    #[test]
+    fn round_trips_a_game() {
+        let sans = [
+            "e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4", "Nf6", "O-O", "Be7", "Re1", "b5", "Bb3",
+            "d6", "c3", "O-O",
+        ];
+        let game = PgnGame::new(
+            "White \"quoted\"",
+            "Black",
+            Position::default(),
+            moves(Board::default(), &sans),
+            "*",
+        );
+
+        let text = game.to_string();
+        assert!(text.contains("1. e4 e5 2. Nf3"));
+        assert_eq!(text.parse::<PgnGame>(), Ok(game));
+    }
+
+    #[test]
+    fn round_trips_a_game_from_a_custom_position() {
+        let start: Position = "4k3/8/8/8/8/8/4P3/4K3 b - - 3 40".parse().unwrap();
+        let game = PgnGame::new(
+            "A",
+            "B",
+            start,
+            moves(start.board, &["Kd7", "e4", "Kc6"]),
+            "1/2-1/2",
+        );
+
+        let text = game.to_string();
+        assert!(text.contains("[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 3 40\"]"));
+        assert!(text.contains("40... Kd7 41. e4 Kc6 1/2-1/2"));
+        assert_eq!(text.parse::<PgnGame>(), Ok(game));
+    }
+
+    #[test]
+    fn round_trips_the_comment_after_the_last_move() {
+        let mut game = PgnGame::new(
+            "A",
+            "B",
+            Position::default(),
+            moves(Board::default(), &["f3", "e5", "g4", "Qh4#"]),
+            "0-1",
+        );
+        game.comment = Some("Black wins by checkmate".to_string());
+
+        let text = game.to_string();
+        assert!(text.contains("Qh4# {Black wins by checkmate} 0-1"));
+        assert_eq!(text.parse::<PgnGame>(), Ok(game));
+    }
+
+    #[test]
+    fn skips_comments_variations_and_annotations() {
+        let game: PgnGame =
+            "[Event \"Test\"]\n\n1. e4 {best by test} e5 (1... c5 2. Nf3) 2. Nf3 $1 ; done\n1-0"
+                .parse()
+                .unwrap();
+        assert_eq!(game.tag("Event"), Some("Test"));
+        assert_eq!(game.moves, moves(Board::default(), &["e4", "e5", "Nf3"]));
+        assert_eq!(game.result, "1-0");
+        assert_eq!(game.comment, None);
+    }
+
+    #[test]
+    fn reports_where_errors_are() {
+        assert_eq!(error_at("1. e4 e5 2. Ke3 *"), (1, 13));
+        assert_eq!(error_at("[Event \"x\"]\n\n1. e4 e5\n2. Nf3 Nf3 *"), (4, 8));
+        assert_eq!(error_at("1. e4 {never closed"), (1, 7));
+        assert_eq!(error_at("[Event \"x\"]\n[Site \"y\n1. e4 *"), (2, 1));
+        assert_eq!(error_at("1. e4 ) e5 *"), (1, 7));
+        assert_eq!(error_at("[Event \"x\"]\n\n1. e4 e5\n2. Nf3 Nc6\n"), (5, 1));
+        assert_eq!(error_at("1. e4 (1. d4"), (1, 13));
+    }
+}
@@ -0,0 +1,288 @@
+//! Standard Algebraic Notation, as used in PGN files and move lists
+
+use chess::{Board, BoardStatus, ChessMove, File, MoveGen, Piece, Square, EMPTY};
+
+use std::str::FromStr;
+
+/// Writes `chess_move` (which must be legal on `board`) in SAN, e.g. `Nbd7`, `exd6` or `O-O+`
+pub fn to_san(board: &Board, chess_move: ChessMove) -> String {
+    let source = chess_move.get_source();
+    let dest = chess_move.get_dest();
+    let piece = board
+        .piece_on(source)
+        .expect("A legal move starts on a piece");
+
+    let mut san = String::new();
+
+    let file_distance = source.get_file().to_index() as isize - dest.get_file().to_index() as isize;
+    if piece == Piece::King && file_distance.abs() == 2 {
+        san.push_str(if dest.get_file() == File::G {
+            "O-O"
+        } else {
+            "O-O-O"
+        });
+    } else {
+        // En passant is the only capture onto an empty square, and only pawns change file doing it
+        let is_capture = board.piece_on(dest).is_some()
+            || (piece == Piece::Pawn && source.get_file() != dest.get_file());
+
+        if piece == Piece::Pawn {
+            if is_capture {
+                san.push_str(&file_name(source.get_file()));
+            }
+        } else {
+            san.push_str(&piece_letter(piece));
+            san.push_str(&disambiguation(board, chess_move, piece));
+        }
+
+        if is_capture {
+            san.push('x');
+        }
+        san.push_str(&dest.to_string());
+
+        if let Some(promotion) = chess_move.get_promotion() {
+            san.push('=');
+            san.push_str(&piece_letter(promotion));
+        }
+    }
+
+    let after = board.make_move_new(chess_move);
+    if *after.checkers() != EMPTY {
+        san.push(if after.status() == BoardStatus::Checkmate {
+            '#'
+        } else {
+            '+'
+        });
+    }
+
+    san
+}
+
+/// Finds the legal move on `board` written as `text` in SAN.
+/// Check markers and annotations (`+`, `#`, `!`, `?`) are optional,
+/// as are the `=` before a promotion, the `x` of a capture and the distinction between `O-O` and `0-0`.
+/// More disambiguation than needed, as in `Ngf3`, is accepted as long as it fits the move.
+pub fn from_san(board: &Board, text: &str) -> Option<ChessMove> {
+    let text = text
+        .trim_end_matches(['+', '#', '!', '?'])
+        .replace('0', "O");
+
+    let castle_file = match text.as_str() {
+        "O-O" => Some(File::G),
+        "O-O-O" => Some(File::C),
+        _ => None,
+    };
+    if let Some(file) = castle_file {
+        return MoveGen::new_legal(board).find(|chess_move| {
+            let source = chess_move.get_source();
+            board.piece_on(source) == Some(Piece::King)
+                && source.get_file() == File::E
+                && chess_move.get_dest().get_file() == file
+        });
+    }
+
+    let mut chars: Vec<char> = text.chars().filter(|&c| c != 'x' && c != '=').collect();
+    let piece = match chars.first().copied().and_then(piece_from_letter) {
+        Some(piece) => {
+            chars.remove(0);
+            piece
+        }
+        None => Piece::Pawn,
+    };
+    let promotion = match chars.last().copied().and_then(piece_from_letter) {
+        Some(promotion) => {
+            chars.pop();
+            Some(promotion)
+        }
+        None => None,
+    };
+
+    if chars.len() < 2 {
+        return None;
+    }
+    let dest: String = chars.split_off(chars.len() - 2).into_iter().collect();
+    let dest = Square::from_str(&dest).ok()?;
+
+    // Whatever is left says which file and/or rank the piece comes from
+    let mut from_file = None;
+    let mut from_rank = None;
+    for c in chars {
+        match c {
+            'a'..='h' if from_file.is_none() => from_file = Some(c as usize - 'a' as usize),
+            '1'..='8' if from_rank.is_none() => from_rank = Some(c as usize - '1' as usize),
+            _ => return None,
+        }
+    }
+
+    let mut candidates = MoveGen::new_legal(board).filter(|chess_move| {
+        let source = chess_move.get_source();
+        chess_move.get_dest() == dest
+            && board.piece_on(source) == Some(piece)
+            && chess_move.get_promotion() == promotion
+            && from_file.map_or(true, |file| source.get_file().to_index() == file)
+            && from_rank.map_or(true, |rank| source.get_rank().to_index() == rank)
+    });
+    let chess_move = candidates.next()?;
+    // Too little disambiguation matches several moves, and none of them can be the one meant
+    match candidates.next() {
+        Some(_) => None,
+        None => Some(chess_move),
+    }
+}
+
+fn piece_from_letter(letter: char) -> Option<Piece> {
+    match letter {
+        'N' => Some(Piece::Knight),
+        'B' => Some(Piece::Bishop),
+        'R' => Some(Piece::Rook),
+        'Q' => Some(Piece::Queen),
+        'K' => Some(Piece::King),
+        _ => None,
+    }
+}
+
+/// The source file and/or rank needed to tell `chess_move` apart
+/// from other moves of the same kind of piece to the same square
+fn disambiguation(board: &Board, chess_move: ChessMove, piece: Piece) -> String {
+    let source = chess_move.get_source();
+    let rivals: Vec<_> = MoveGen::new_legal(board)
+        .filter(|other| {
+            other.get_dest() == chess_move.get_dest()
+                && other.get_source() != source
+                && board.piece_on(other.get_source()) == Some(piece)
+        })
+        .map(|other| other.get_source())
+        .collect();
+
+    let square = source.to_string();
+    if rivals.is_empty() {
+        String::new()
+    } else if rivals
+        .iter()
+        .all(|rival| rival.get_file() != source.get_file())
+    {
+        square[..1].to_string()
+    } else if rivals
+        .iter()
+        .all(|rival| rival.get_rank() != source.get_rank())
+    {
+        square[1..].to_string()
+    } else {
+        square
+    }
+}
+
+fn piece_letter(piece: Piece) -> String {
+    piece.to_string(chess::Color::White)
+}
+
+fn file_name(file: File) -> String {
+    ((b'a' + file.to_index() as u8) as char).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(fen: &str) -> Board {
+        Board::from_str(fen).unwrap()
+    }
+
+    fn san(fen: &str, chess_move: &str) -> String {
+        to_san(&board(fen), ChessMove::from_str(chess_move).unwrap())
+    }
+
+    /// Two knights can reach d7
+    const KNIGHTS: &str = "1n2k3/8/5n2/8/8/8/8/4K3 b - - 0 1";
+    /// Two rooks on the e-file can reach e2
+    const ROOKS: &str = "k7/8/8/8/8/4R3/8/4R1K1 w - - 0 1";
+    /// Three queens can reach e1, sharing a file with one and a rank with another
+    const QUEENS: &str = "1k6/8/8/8/4Q2Q/8/8/K6Q w - - 0 1";
+
+    #[test]
+    fn disambiguates_by_file_then_rank_then_both() {
+        assert_eq!(san(KNIGHTS, "b8d7"), "Nbd7");
+        assert_eq!(san(KNIGHTS, "f6d7"), "Nfd7");
+        assert_eq!(san(ROOKS, "e1e2"), "R1e2");
+        assert_eq!(san(ROOKS, "e3e2"), "R3e2");
+        assert_eq!(san(QUEENS, "h4e1"), "Qh4e1");
+    }
+
+    #[test]
+    fn writes_castling_promotion_and_en_passant() {
+        let castling = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(san(castling, "e1g1"), "O-O");
+        assert_eq!(san(castling, "e1c1"), "O-O-O");
+
+        let promotion = "k7/4P3/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(san(promotion, "e7e8q"), "e8=Q+");
+        assert_eq!(san(promotion, "e7e8n"), "e8=N");
+
+        assert_eq!(san("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), "exd6");
+    }
+
+    #[test]
+    fn marks_check_and_mate() {
+        let fools_mate = "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2";
+        assert_eq!(san(fools_mate, "d8h4"), "Qh4#");
+        assert_eq!(san(fools_mate, "f8b4"), "Bb4");
+        assert_eq!(san("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a8"), "Ra8+");
+    }
+
+    #[test]
+    fn reads_back_every_move_it_writes() {
+        for fen in [
+            KNIGHTS,
+            ROOKS,
+            QUEENS,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "4k3/1P6/8/3pP3/8/8/8/4K3 w - d6 0 1",
+        ]
+        .iter()
+        {
+            let board = board(fen);
+            for chess_move in MoveGen::new_legal(&board) {
+                let text = to_san(&board, chess_move);
+                assert_eq!(
+                    from_san(&board, &text),
+                    Some(chess_move),
+                    "{} in {}",
+                    text,
+                    fen
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn reads_loose_notation() {
+        let start = Board::default();
+        let knight = ChessMove::from_str("g1f3").unwrap();
+        assert_eq!(from_san(&start, "Nf3"), Some(knight));
+        assert_eq!(from_san(&start, "Ngf3"), Some(knight));
+        assert_eq!(from_san(&start, "N1f3"), Some(knight));
+        assert_eq!(from_san(&start, "Ng1f3!?"), Some(knight));
+        assert_eq!(from_san(&start, "Nbf3"), None);
+
+        let promotion = board("k7/4P3/8/8/8/8/8/4K3 w - - 0 1");
+        let queen = ChessMove::from_str("e7e8q").unwrap();
+        assert_eq!(from_san(&promotion, "e8Q"), Some(queen));
+        assert_eq!(from_san(&promotion, "e8=Q+"), Some(queen));
+        assert_eq!(from_san(&promotion, "e8"), None);
+
+        let castling = board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert_eq!(
+            from_san(&castling, "0-0"),
+            Some(ChessMove::from_str("e1g1").unwrap())
+        );
+    }
+
+    #[test]
+    fn rejects_ambiguous_and_unknown_moves() {
+        assert_eq!(from_san(&board(KNIGHTS), "Nd7"), None);
+        assert_eq!(from_san(&board(ROOKS), "Re2"), None);
+        assert_eq!(from_san(&Board::default(), "e5"), None);
+        assert_eq!(from_san(&Board::default(), "Zf3"), None);
+        assert_eq!(from_san(&Board::default(), ""), None);
+    }
+}
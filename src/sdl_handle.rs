@@ -3,17 +3,20 @@ use sdl2::{
     pixels::Color,
     rect::{Point, Rect},
     render::TextureCreator,
-    video::WindowContext,
+    ttf::{self, Sdl2TtfContext},
+    video::{FullscreenType, WindowContext},
     EventPump, Sdl,
 };
 
-use std::{env::current_dir, path::PathBuf};
+use std::{env::current_dir, path::PathBuf, rc::Rc};
 
 use crate::drawable::{Drawable, Renderer};
 use crate::utils;
 
 pub struct SDLHandle {
     _image_context: Sdl2ImageContext,
+    /// Shared, since fonts borrow it while the handle is borrowed mutably for drawing
+    ttf_context: Rc<Sdl2TtfContext>,
     sdl_context: Sdl,
     canvas: Renderer,
     app_directory: PathBuf,
@@ -25,16 +28,21 @@ impl SDLHandle {
     pub fn init(
         window_title: &str,
         (width, height): (u32, u32),
+        minimum_size: (u32, u32),
         image_init: image::InitFlag,
     ) -> Result<SDLHandle, String> {
         let sdl_context = sdl2::init()?;
         let video = sdl_context.video()?;
 
-        let window = video
+        let mut window = video
             .window(window_title, width, height)
             .position_centered()
+            .resizable()
             .build()
             .map_err(|e| format!("Error building window: {}", e))?;
+        if let Err(e) = window.set_minimum_size(minimum_size.0, minimum_size.1) {
+            eprintln!("{}", e);
+        }
 
         let mut canvas = window
             .into_canvas()
@@ -42,32 +50,63 @@ impl SDLHandle {
             .build()
             .map_err(|e| format!("Error building canvas: {}", e))?;
 
-        if let Err(e) = canvas.set_logical_size(width, height) {
-            eprintln!("{}", e);
-        }
-
         canvas.set_draw_color(CLEAR_COLOR);
         canvas.clear();
         canvas.present();
 
         let _image_context = image::init(image_init)?;
+        let ttf_context = Rc::new(ttf::init().map_err(|e| e.to_string())?);
 
         let app_directory = current_dir().map_err(|e| format!("{}", e))?;
 
         Ok(Self {
             _image_context,
+            ttf_context,
             sdl_context,
             canvas,
             app_directory,
         })
     }
 
+    /// Used to load fonts
+    pub fn ttf_context(&self) -> Rc<Sdl2TtfContext> {
+        self.ttf_context.clone()
+    }
+
     pub fn event_pump(&self) -> Result<EventPump, String> {
         self.sdl_context.event_pump()
     }
 
+    /// The text currently on the system clipboard, if there is any
+    pub fn clipboard_text(&self) -> Result<String, String> {
+        let clipboard = self.sdl_context.video()?.clipboard();
+        if clipboard.has_clipboard_text() {
+            clipboard.clipboard_text()
+        } else {
+            Err("The clipboard does not contain any text".to_string())
+        }
+    }
+
+    pub fn set_title(&mut self, title: &str) -> Result<(), String> {
+        self.canvas
+            .window_mut()
+            .set_title(title)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Switches between a window and covering the whole screen
+    pub fn toggle_fullscreen(&mut self) -> Result<(), String> {
+        let window = self.canvas.window_mut();
+        let fullscreen = match window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            FullscreenType::True | FullscreenType::Desktop => FullscreenType::Off,
+        };
+        window.set_fullscreen(fullscreen)
+    }
+
+    /// The size of the window in pixels, which changes as it is resized
     pub fn draw_size(&self) -> (u32, u32) {
-        self.canvas.logical_size()
+        self.canvas.output_size().unwrap_or((0, 0))
     }
 
     pub fn center_of_draw(&self) -> (u32, u32) {
@@ -85,6 +124,7 @@ impl SDLHandle {
         thing.draw_at(&mut self.canvas, pos)
     }
 
+    #[allow(dead_code)]
     pub fn draw_on<D: Drawable>(&mut self, area: Rect, thing: &D) -> Result<(), String> {
         thing.draw_on(&mut self.canvas, area)
     }
@@ -0,0 +1,389 @@
+//! Matches between two engine configurations, for measuring whether an engine change helps
+
+use chess::{Board, BoardStatus, Color};
+
+use std::{fmt, str::FromStr, sync::atomic::AtomicBool, time::Duration};
+
+use crate::{
+    ai::{self, AIState, Difficulty, SearchLimits},
+    draw,
+    fen::Position,
+    game::Outcome,
+    pgn::PgnGame,
+};
+
+/// Move time used when an engine is given no limits at all
+const DEFAULT_MOVE_TIME: Duration = Duration::from_millis(100);
+
+/// z-score of the 95% confidence interval reported with the Elo difference
+const CONFIDENCE_Z: f64 = 1.96;
+
+/// How an engine is set up for a match
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EngineConfig {
+    /// Written as the player's name in the PGN; defaults to the settings it was read from
+    pub name: String,
+    pub hash_megabytes: usize,
+    /// Applied to every move the engine makes
+    pub limits: SearchLimits,
+    pub difficulty: Difficulty,
+    /// Makes the engine's random choices the same from one match to the next
+    pub seed: Option<u64>,
+}
+
+impl FromStr for EngineConfig {
+    type Err = String;
+
+    /// Reads comma-separated settings such as `name=new,hash=64,depth=6,nodes=50000,movetime=200`
+    /// or `difficulty=easy,seed=7`.
+    /// Settings that are left out keep their defaults.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut config = Self {
+            name: text.to_string(),
+            hash_megabytes: ai::DEFAULT_HASH_MEGABYTES,
+            limits: SearchLimits::default(),
+            difficulty: Difficulty::default(),
+            seed: None,
+        };
+
+        for setting in text.split(',').filter(|setting| !setting.is_empty()) {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("Expected key=value, found {}", setting))?;
+            let number = || {
+                value
+                    .parse::<u64>()
+                    .map_err(|e| format!("Invalid {} {}: {}", key, value, e))
+            };
+            match key {
+                "name" => config.name = value.to_string(),
+                "hash" => config.hash_megabytes = number()?.max(1) as usize,
+                "depth" => config.limits.depth = Some(number()?.min(u8::MAX.into()) as u8),
+                "nodes" => config.limits.nodes = Some(number()?),
+                "movetime" => config.limits.move_time = Some(Duration::from_millis(number()?)),
+                "difficulty" => config.difficulty = value.parse()?,
+                "seed" => config.seed = Some(number()?),
+                _ => return Err(format!("Unknown engine setting {}", key)),
+            }
+        }
+
+        if config.limits == SearchLimits::infinite() {
+            // Otherwise the first search would never end
+            config.limits.move_time = Some(DEFAULT_MOVE_TIME);
+        }
+
+        Ok(config)
+    }
+}
+
+/// A finished game, from the point of view of the first engine of the match
+#[derive(Clone, Debug)]
+pub struct GameRecord {
+    pub pgn: PgnGame,
+    pub outcome: Outcome,
+    /// 1 for a win of the first engine, 0.5 for a draw and 0 for a loss
+    pub score: f64,
+}
+
+/// Wins, draws and losses of the first engine against the second
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MatchStats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchStats {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// The fraction of points the first engine scored
+    pub fn score(&self) -> f64 {
+        (f64::from(self.wins) + f64::from(self.draws) / 2.0) / f64::from(self.games().max(1))
+    }
+
+    /// The Elo difference of the first engine over the second,
+    /// along with the margin of its 95% confidence interval.
+    /// `None` while the score is 0% or 100%, where the difference is unbounded.
+    pub fn elo_difference(&self) -> Option<(f64, f64)> {
+        let games = f64::from(self.games());
+        let score = self.score();
+        if self.games() == 0 || score <= 0.0 || score >= 1.0 {
+            return None;
+        }
+
+        // The spread of the per-game results around the mean score
+        let variance = (f64::from(self.wins) * (1.0 - score).powi(2)
+            + f64::from(self.draws) * (0.5 - score).powi(2)
+            + f64::from(self.losses) * score.powi(2))
+            / games;
+        let error = (variance / games).sqrt() * CONFIDENCE_Z;
+
+        let low = elo_from_score((score - error).max(f64::EPSILON));
+        let high = elo_from_score((score + error).min(1.0 - f64::EPSILON));
+        Some((elo_from_score(score), (high - low) / 2.0))
+    }
+
+    fn add(&mut self, score: f64) {
+        if score > 0.5 {
+            self.wins += 1;
+        } else if score < 0.5 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+}
+
+impl fmt::Display for MatchStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "+{} ={} -{} ({:.1}%)",
+            self.wins,
+            self.draws,
+            self.losses,
+            self.score() * 100.0
+        )?;
+        match self.elo_difference() {
+            Some((elo, margin)) => write!(f, ", Elo difference {:+.1} +/- {:.1}", elo, margin),
+            None => write!(f, ", Elo difference unbounded"),
+        }
+    }
+}
+
+/// The Elo difference that gives an expected score of `score`
+fn elo_from_score(score: f64) -> f64 {
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+/// A series of games between two engines
+pub struct Match {
+    pub engines: [EngineConfig; 2],
+    /// Each opening is played twice in a row, so both engines get to play both sides of it
+    pub openings: Vec<Position>,
+    pub games: u32,
+}
+
+impl Match {
+    /// Plays all games one after the other, handing each to `report` as soon as it is finished
+    pub fn play<F: FnMut(u32, &GameRecord, &MatchStats)>(&self, mut report: F) -> MatchStats {
+        let mut states = [
+            AIState::with_hash_size(self.engines[0].hash_megabytes),
+            AIState::with_hash_size(self.engines[1].hash_megabytes),
+        ];
+        for (state, engine) in states.iter_mut().zip(&self.engines) {
+            state.set_difficulty(engine.difficulty);
+            if let Some(seed) = engine.seed {
+                state.seed(seed);
+            }
+        }
+        let default_opening = [Position::default()];
+        let openings = if self.openings.is_empty() {
+            &default_opening[..]
+        } else {
+            &self.openings[..]
+        };
+
+        let mut stats = MatchStats::default();
+        for round in 0..self.games {
+            let opening = openings[(round / 2) as usize % openings.len()];
+            // The first engine has White in even rounds
+            let first_is_white = round % 2 == 0;
+
+            for state in &mut states {
+                state.clear();
+            }
+            let record = self.play_game(&mut states, opening, first_is_white, round + 1);
+
+            stats.add(record.score);
+            report(round + 1, &record, &stats);
+        }
+        stats
+    }
+
+    fn play_game(
+        &self,
+        states: &mut [AIState; 2],
+        start: Position,
+        first_is_white: bool,
+        round: u32,
+    ) -> GameRecord {
+        let engine_index = |color: Color| usize::from((color == Color::White) != first_is_white);
+        let stop = AtomicBool::new(false);
+
+        let mut board = start.board;
+        let mut moves = Vec::new();
+        // Positions since the last capture or pawn move, for the draw rules
+        let mut positions = vec![board];
+        let mut halfmove_clock = start.halfmove_clock;
+
+        let outcome = loop {
+            if let Some(outcome) = Outcome::of(&positions, halfmove_clock) {
+                break outcome;
+            }
+
+            let index = engine_index(board.side_to_move());
+            let chess_move = states[index]
+                .search(board, self.engines[index].limits, &stop, |_| {})
+                .expect("An ongoing game has legal moves");
+
+            if draw::is_irreversible(&board, chess_move) {
+                positions.clear();
+                halfmove_clock = 0;
+            }
+            board = board.make_move_new(chess_move);
+            positions.push(board);
+            moves.push(chess_move);
+        };
+
+        let white_score = match outcome.winner() {
+            Some(Color::White) => 1.0,
+            Some(Color::Black) => 0.0,
+            None => 0.5,
+        };
+
+        let white = &self.engines[engine_index(Color::White)].name;
+        let black = &self.engines[engine_index(Color::Black)].name;
+        let mut pgn = PgnGame::new(white, black, start, moves, outcome.result());
+        set_tag(&mut pgn, "Event", "Self-play match");
+        set_tag(&mut pgn, "Round", &round.to_string());
+        pgn.tags
+            .push(("Termination".to_string(), outcome.termination().to_string()));
+        // The tag only allows a few fixed values, so the details go in a comment
+        pgn.comment = Some(outcome.to_string());
+
+        GameRecord {
+            pgn,
+            outcome,
+            score: if first_is_white {
+                white_score
+            } else {
+                1.0 - white_score
+            },
+        }
+    }
+}
+
+fn set_tag(pgn: &mut PgnGame, name: &str, value: &str) {
+    if let Some((_, old)) = pgn.tags.iter_mut().find(|(tag, _)| tag == name) {
+        *old = value.to_string();
+    }
+}
+
+/// Reads opening positions from text with one FEN per line.
+/// Blank lines and lines starting with `#` are skipped.
+pub fn parse_openings(text: &str) -> Result<Vec<Position>, String> {
+    text.lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(index, line)| {
+            let position: Position = line
+                .parse()
+                .map_err(|e| format!("Invalid FEN on line {}: {}", index + 1, e))?;
+            if is_playable(&position.board) {
+                Ok(position)
+            } else {
+                Err(format!("The game is already over on line {}", index + 1))
+            }
+        })
+        .collect()
+}
+
+/// Whether `board` could be the start of a game, i.e. has moves and is not already drawn
+fn is_playable(board: &Board) -> bool {
+    board.status() == BoardStatus::Ongoing && !draw::insufficient_material(board)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(wins: u32, draws: u32, losses: u32) -> MatchStats {
+        MatchStats {
+            wins,
+            draws,
+            losses,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 0.01,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn scores() {
+        assert_close(stats(3, 0, 1).score(), 0.75);
+        assert_close(stats(5, 6, 3).score(), 8.0 / 14.0);
+        assert_close(stats(0, 4, 0).score(), 0.5);
+        assert_close(stats(0, 0, 0).score(), 0.0);
+    }
+
+    #[test]
+    fn elo_of_known_scores() {
+        let (elo, _) = stats(3, 0, 1).elo_difference().unwrap();
+        assert_close(elo, 190.85);
+        let (elo, _) = stats(1, 2, 3).elo_difference().unwrap();
+        assert_close(elo, -120.41);
+
+        let (elo, margin) = stats(30, 40, 30).elo_difference().unwrap();
+        assert_close(elo, 0.0);
+        assert_close(margin, 53.16);
+
+        let (elo, margin) = stats(60, 20, 20).elo_difference().unwrap();
+        assert_close(elo, 147.19);
+        assert_close(margin, 66.01);
+    }
+
+    #[test]
+    fn all_wins_or_all_losses_are_unbounded() {
+        for stats in &[stats(5, 0, 0), stats(0, 0, 5), stats(0, 0, 0)] {
+            assert_eq!(stats.elo_difference(), None);
+            assert!(stats.score().is_finite());
+            let text = stats.to_string();
+            assert!(!text.contains("NaN") && !text.contains("inf"), "{}", text);
+        }
+
+        // One draw is enough for a finite, if wide, interval
+        let (elo, margin) = stats(9, 1, 0).elo_difference().unwrap();
+        assert!(elo.is_finite() && margin.is_finite());
+    }
+
+    #[test]
+    fn reads_engine_configs() {
+        let config: EngineConfig = "name=new,hash=64,depth=6,difficulty=easy,seed=7"
+            .parse()
+            .unwrap();
+        assert_eq!(config.name, "new");
+        assert_eq!(config.hash_megabytes, 64);
+        assert_eq!(config.limits.depth, Some(6));
+        // A depth limit is enough to end every search
+        assert_eq!(config.limits.move_time, None);
+        assert_eq!(config.difficulty, Difficulty::Easy);
+        assert_eq!(config.seed, Some(7));
+
+        let config: EngineConfig = "".parse().unwrap();
+        assert_eq!(config.limits.move_time, Some(DEFAULT_MOVE_TIME));
+    }
+
+    #[test]
+    fn rejects_bad_engine_configs() {
+        for text in &[
+            "depth",
+            "depth=deep",
+            "hash=-1",
+            "movetime=1.5",
+            "difficulty=impossible",
+            "colour=white",
+        ] {
+            assert!(text.parse::<EngineConfig>().is_err(), "{}", text);
+        }
+    }
+}
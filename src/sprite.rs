@@ -1,4 +1,5 @@
 use sdl2::{
+    hint,
     image::LoadTexture,
     rect::Rect,
     render::{Texture, TextureQuery},
@@ -11,38 +12,79 @@ use crate::{
     utils,
 };
 
-/// Load an image as a spritesheet with a grid that starts on 0,0
-pub fn load_grid_sprite_sheet<Loader: LoadTexture, P: AsRef<Path>>(
-    loader: &Loader,
-    filename: P,
+/// How SDL samples a texture drawn at another size than its own, which is fixed when it is loaded
+const SCALE_QUALITY_HINT: &str = "SDL_RENDER_SCALE_QUALITY";
+
+/// An image cut into a grid of square cells, each holding one sprite
+pub struct SpriteSheet<'a> {
+    texture: Rc<Texture<'a>>,
+    smooth_texture: Rc<Texture<'a>>,
     grid_size: u32,
-) -> Result<Vec<Sprite<'_>>, String> {
-    let texture = Rc::new(loader.load_texture(filename.as_ref())?);
+    /// The number of whole cells across and down the image
+    columns: u32,
+    rows: u32,
+}
 
-    let (width, height) = texture_size(&texture);
+impl<'a> SpriteSheet<'a> {
+    /// Load an image as a spritesheet with a grid that starts on 0,0
+    pub fn load<Loader: LoadTexture, P: AsRef<Path>>(
+        loader: &'a Loader,
+        filename: P,
+        grid_size: u32,
+    ) -> Result<Self, String> {
+        if grid_size == 0 {
+            return Err("The grid size must be at least one pixel".to_string());
+        }
 
-    let mut sprites = vec![];
+        // Loaded twice: blurring the pixels would only soften the sprites when they are scaled
+        // by a whole factor, but any other factor looks uneven without it
+        hint::set(SCALE_QUALITY_HINT, "linear");
+        let smooth_texture = Rc::new(loader.load_texture(filename.as_ref())?);
+        hint::set(SCALE_QUALITY_HINT, "nearest");
+        let texture = Rc::new(loader.load_texture(filename.as_ref())?);
 
-    for y in (0..height).filter(|val| val % grid_size == 0) {
-        for x in (0..width).filter(|val| val % grid_size == 0) {
-            let (x, y): (i32, i32) = utils::map_tuple((x, y), |val| val.try_into().unwrap());
-            let rect: Rect = (x, y, grid_size, grid_size).into();
-            sprites.push(Sprite::from_sheet(texture.clone(), rect)?);
-        }
+        let (width, height) = texture_size(&texture);
+        Ok(Self {
+            texture,
+            smooth_texture,
+            grid_size,
+            columns: width / grid_size,
+            rows: height / grid_size,
+        })
     }
 
-    Ok(sprites)
+    /// The sprite in the given column and row, counting from the top left
+    pub fn sprite(&self, (column, row): (u32, u32)) -> Result<Sprite<'a>, String> {
+        if column >= self.columns || row >= self.rows {
+            return Err(format!(
+                "cell [{}, {}] is outside the sheet, which has {} columns and {} rows of {} pixels",
+                column, row, self.columns, self.rows, self.grid_size
+            ));
+        }
+
+        let (x, y): (i32, i32) = utils::map_tuple((column, row), |val| {
+            (val * self.grid_size).try_into().unwrap()
+        });
+        let rect: Rect = (x, y, self.grid_size, self.grid_size).into();
+        Sprite::from_sheet(self.texture.clone(), self.smooth_texture.clone(), rect)
+    }
 }
 
 /// A sprite is a square mask on another texture
 #[derive(Clone)]
 pub struct Sprite<'a> {
     sheet: Rc<Texture<'a>>,
+    /// The same sheet with linear filtering, for drawing at sizes that are not a multiple of the mask
+    smooth_sheet: Rc<Texture<'a>>,
     mask: Rect,
 }
 
 impl<'a> Sprite<'a> {
-    fn from_sheet(sheet: Rc<Texture<'a>>, rect: Rect) -> Result<Self, String> {
+    fn from_sheet(
+        sheet: Rc<Texture<'a>>,
+        smooth_sheet: Rc<Texture<'a>>,
+        rect: Rect,
+    ) -> Result<Self, String> {
         let (sheet_width, sheet_height) =
             utils::map_tuple(texture_size(&sheet), |val| val.try_into().unwrap());
 
@@ -55,14 +97,25 @@ impl<'a> Sprite<'a> {
         } else if rect.top() > sheet_height {
             Err("Mask is too far down".to_string())
         } else {
-            Ok(Sprite { sheet, mask: rect })
+            Ok(Sprite {
+                sheet,
+                smooth_sheet,
+                mask: rect,
+            })
         }
     }
 }
 
 impl Drawable for Sprite<'_> {
     fn draw_on(&self, dest: &mut Renderer, target_area: Rect) -> Result<(), String> {
-        dest.copy(&self.sheet, Some(self.mask), Some(target_area))
+        let whole_factor = target_area.width() % self.mask.width() == 0
+            && target_area.height() % self.mask.height() == 0;
+        let sheet = if whole_factor {
+            &self.sheet
+        } else {
+            &self.smooth_sheet
+        };
+        dest.copy(sheet, Some(self.mask), Some(target_area))
     }
 }
 
@@ -0,0 +1,162 @@
+use sdl2::{
+    pixels::Color,
+    rect::{Point, Rect},
+    render::{Texture, TextureCreator, TextureQuery},
+    ttf::{self, Sdl2TtfContext},
+    video::WindowContext,
+};
+
+use std::{cell::RefCell, collections::HashMap, convert::TryFrom, path::Path, rc::Rc};
+
+use crate::drawable::{Drawable, Renderer};
+
+/// A single character rendered in one color
+struct Glyph<'a> {
+    /// `None` for spaces, which are not drawn
+    texture: Option<Texture<'a>>,
+    /// How far the next character starts to the right of this one
+    advance: i32,
+}
+
+/// A font along with every glyph rendered from it so far,
+/// so each character only has to be rendered once per color
+pub struct Font<'a> {
+    font: ttf::Font<'a, 'static>,
+    texture_creator: &'a TextureCreator<WindowContext>,
+    glyphs: RefCell<HashMap<(char, Color), Rc<Glyph<'a>>>>,
+}
+
+impl<'a> Font<'a> {
+    pub fn load<P: AsRef<Path>>(
+        ttf_context: &'a Sdl2TtfContext,
+        texture_creator: &'a TextureCreator<WindowContext>,
+        path: P,
+        point_size: u16,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            font: ttf_context.load_font(path, point_size)?,
+            texture_creator,
+            glyphs: Default::default(),
+        })
+    }
+
+    /// The height of a line of text in pixels
+    pub fn height(&self) -> u32 {
+        u32::try_from(self.font.height()).unwrap_or(0)
+    }
+
+    /// The size `text` takes up when drawn, in pixels
+    pub fn size_of(&self, text: &str) -> Result<(u32, u32), String> {
+        let mut width = 0;
+        for c in text.chars() {
+            width += self.advance(c)?;
+        }
+        Ok((u32::try_from(width).unwrap_or(0), self.height()))
+    }
+
+    /// How far the character after `c` starts to the right of it
+    fn advance(&self, c: char) -> Result<i32, String> {
+        match self.font.find_glyph_metrics(c) {
+            Some(metrics) => Ok(metrics.advance),
+            None => {
+                let (width, _) = self
+                    .font
+                    .size_of_char(c)
+                    .map_err(|e| format!("Could not measure '{}': {}", c, e))?;
+                Ok(i32::try_from(width).unwrap())
+            }
+        }
+    }
+
+    /// Renders `c` the first time it is asked for in `color`, then hands out the cached glyph
+    fn glyph(&self, c: char, color: Color) -> Result<Rc<Glyph<'a>>, String> {
+        if let Some(glyph) = self.glyphs.borrow().get(&(c, color)) {
+            return Ok(glyph.clone());
+        }
+
+        let advance = self.advance(c)?;
+        let texture = if c.is_whitespace() {
+            None
+        } else {
+            let surface = self
+                .font
+                .render_char(c)
+                .blended(color)
+                .map_err(|e| format!("Could not render '{}': {}", c, e))?;
+            let texture = self
+                .texture_creator
+                .create_texture_from_surface(&surface)
+                .map_err(|e| e.to_string())?;
+            Some(texture)
+        };
+
+        let glyph = Rc::new(Glyph { texture, advance });
+        self.glyphs.borrow_mut().insert((c, color), glyph.clone());
+        Ok(glyph)
+    }
+
+    /// Draws `text` with its top left corner at `top_left`
+    fn draw_text(
+        &self,
+        dest: &mut Renderer,
+        text: &str,
+        color: Color,
+        top_left: Point,
+    ) -> Result<(), String> {
+        let mut x = top_left.x();
+        for c in text.chars() {
+            let glyph = self.glyph(c, color)?;
+            if let Some(texture) = &glyph.texture {
+                let TextureQuery { width, height, .. } = texture.query();
+                dest.copy(
+                    texture,
+                    None,
+                    Some(Rect::new(x, top_left.y(), width, height)),
+                )?;
+            }
+            x += glyph.advance;
+        }
+        Ok(())
+    }
+}
+
+/// A line of text in one color
+pub struct Label<'f, 'a> {
+    font: &'f Font<'a>,
+    text: String,
+    color: Color,
+}
+
+impl<'f, 'a> Label<'f, 'a> {
+    pub fn new<S: Into<String>>(font: &'f Font<'a>, text: S, color: Color) -> Self {
+        Self {
+            font,
+            text: text.into(),
+            color,
+        }
+    }
+
+    pub fn size(&self) -> Result<(u32, u32), String> {
+        self.font.size_of(&self.text)
+    }
+}
+
+impl Drawable for Label<'_, '_> {
+    /// Left-aligns the text in `area`, centered vertically
+    fn draw_on(&self, dest: &mut Renderer, area: Rect) -> Result<(), String> {
+        let (_, height) = self.size()?;
+        let top = area.center().y() - i32::try_from(height / 2).unwrap();
+        self.font
+            .draw_text(dest, &self.text, self.color, Point::new(area.left(), top))
+    }
+
+    /// Centers the text on `pos`
+    fn draw_at(&self, dest: &mut Renderer, pos: Point) -> Result<(), String> {
+        let (width, height) = self.size()?;
+        let top_left = pos.offset(
+            -i32::try_from(width / 2).unwrap(),
+            -i32::try_from(height / 2).unwrap(),
+        );
+        self.font.draw_text(dest, &self.text, self.color, top_left)
+    }
+}
@@ -0,0 +1,347 @@
+use chess::{Piece, ALL_COLORS, ALL_PIECES};
+
+use sdl2::{image::LoadTexture, pixels::Color};
+
+use serde::{de, Deserialize, Deserializer};
+
+use std::{
+    fs, iter,
+    path::{Path, PathBuf},
+};
+
+use crate::sprite::{Sprite, SpriteSheet};
+
+/// A cell of a sprite sheet, as the column and row counted from the top left
+pub type Cell = (u32, u32);
+
+/// The colors the board and the panels around it are drawn in.
+/// Colors with an alpha below 255 are blended over what is under them.
+///
+/// In a manifest, each is written as `"#rrggbb"`, or `"#rrggbbaa"` to make it translucent.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// Outline of the selected square
+    #[serde(deserialize_with = "hex_color")]
+    pub selected: Color,
+    /// Dot on an empty square the selected piece can move to
+    #[serde(deserialize_with = "hex_color")]
+    pub move_dot: Color,
+    /// Ring on a square where the selected piece can capture
+    #[serde(deserialize_with = "hex_color")]
+    pub capture_ring: Color,
+    /// Tint over the squares the last move was played from and to
+    #[serde(deserialize_with = "hex_color")]
+    pub last_move: Color,
+    /// Glow around a king in check
+    #[serde(deserialize_with = "hex_color")]
+    pub check: Color,
+    /// Outline around the board while the AI is searching
+    #[serde(deserialize_with = "hex_color")]
+    pub thinking: Color,
+    /// Background of the promotion picker, so its pieces stand out from the board
+    #[serde(deserialize_with = "hex_color")]
+    pub promotion_background: Color,
+    /// Background of the banner shown when the game is over, and of its buttons
+    #[serde(deserialize_with = "hex_color")]
+    pub banner_background: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub new_game: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub quit: Color,
+    /// Fill of a clock bar while its clock is running, stopped, or low on time
+    #[serde(deserialize_with = "hex_color")]
+    pub clock_running: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub clock_stopped: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub clock_low: Color,
+    /// Background of the move list, and of the move the board is showing
+    #[serde(deserialize_with = "hex_color")]
+    pub panel_background: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub current_move: Color,
+    /// Color of all text, and of the outlines around the panels
+    #[serde(deserialize_with = "hex_color")]
+    pub text: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            selected: Color::MAGENTA,
+            move_dot: Color::RGBA(20, 85, 30, 130),
+            capture_ring: Color::RGBA(20, 85, 30, 160),
+            last_move: Color::RGBA(255, 235, 60, 110),
+            check: Color::RGBA(230, 20, 20, 70),
+            thinking: Color::YELLOW,
+            promotion_background: Color::RGB(240, 240, 240),
+            banner_background: Color::RGB(200, 200, 200),
+            new_game: Color::RGB(60, 180, 75),
+            quit: Color::RGB(220, 50, 50),
+            clock_running: Color::RGB(60, 180, 75),
+            clock_stopped: Color::RGB(150, 150, 150),
+            clock_low: Color::RGB(220, 50, 50),
+            panel_background: Color::RGB(240, 240, 240),
+            current_move: Color::RGB(255, 220, 120),
+            text: Color::BLACK,
+        }
+    }
+}
+
+/// A theme as described by a manifest file: its colors,
+/// and which cell of which sprite sheet holds each square and piece
+///
+/// Manifests are TOML, e.g.
+///
+/// ```toml
+/// name = "Classic"
+/// sheet = "../sprite_sheet.png"
+/// grid = 32
+///
+/// [squares]
+/// light = [1, 0]
+/// dark = [0, 0]
+///
+/// [pieces]
+/// white_pawn = [1, 1]
+/// black_pawn = [0, 1]
+/// # ... and so on for every piece
+///
+/// [colors]
+/// selected = "#ff00ff"
+/// last_move = "#ffeb3c6e"
+/// ```
+///
+/// Colors that are left out keep their default.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThemeManifest {
+    pub name: String,
+    /// The sprite sheet, relative to the manifest until it is loaded with `load`
+    pub sheet: PathBuf,
+    /// The size of a cell of the sheet in pixels
+    #[serde(rename = "grid")]
+    pub grid_size: u32,
+    pub squares: Squares,
+    pub pieces: Pieces,
+    #[serde(default)]
+    pub colors: Theme,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Squares {
+    pub light: Cell,
+    pub dark: Cell,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pieces {
+    pub white_pawn: Cell,
+    pub white_knight: Cell,
+    pub white_bishop: Cell,
+    pub white_rook: Cell,
+    pub white_queen: Cell,
+    pub white_king: Cell,
+    pub black_pawn: Cell,
+    pub black_knight: Cell,
+    pub black_bishop: Cell,
+    pub black_rook: Cell,
+    pub black_queen: Cell,
+    pub black_king: Cell,
+}
+
+impl Pieces {
+    pub fn cell(&self, color: chess::Color, piece: Piece) -> Cell {
+        use chess::Color::{Black, White};
+        match (color, piece) {
+            (White, Piece::Pawn) => self.white_pawn,
+            (White, Piece::Knight) => self.white_knight,
+            (White, Piece::Bishop) => self.white_bishop,
+            (White, Piece::Rook) => self.white_rook,
+            (White, Piece::Queen) => self.white_queen,
+            (White, Piece::King) => self.white_king,
+            (Black, Piece::Pawn) => self.black_pawn,
+            (Black, Piece::Knight) => self.black_knight,
+            (Black, Piece::Bishop) => self.black_bishop,
+            (Black, Piece::Rook) => self.black_rook,
+            (Black, Piece::Queen) => self.black_queen,
+            (Black, Piece::King) => self.black_king,
+        }
+    }
+}
+
+impl ThemeManifest {
+    /// Reads the manifest at `path`, with the sheet found relative to it
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let in_file = |e: String| format!("{}: {}", path.display(), e);
+        let text = fs::read_to_string(path).map_err(|e| in_file(e.to_string()))?;
+        let mut manifest: Self = toml::from_str(&text).map_err(|e| in_file(e.to_string()))?;
+        if let Some(directory) = path.parent() {
+            manifest.sheet = directory.join(&manifest.sheet);
+        }
+        Ok(manifest)
+    }
+}
+
+/// Reads a color written as `"#rrggbb"`, or `"#rrggbbaa"` to make it translucent
+fn hex_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    let invalid = || {
+        de::Error::custom(format!(
+            "expected a color such as \"#ff00ff\", found \"{}\"",
+            text
+        ))
+    };
+    let hex = text.strip_prefix('#').ok_or_else(invalid)?;
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+
+    let channel = |index: usize| u8::from_str_radix(&hex[2 * index..2 * index + 2], 16).unwrap();
+    let alpha = if hex.len() == 8 { channel(3) } else { 255 };
+    Ok(Color::RGBA(channel(0), channel(1), channel(2), alpha))
+}
+
+/// A piece as it is named in error messages, e.g. `white knight`
+fn piece_name(color: chess::Color, piece: Piece) -> String {
+    let piece = match piece {
+        Piece::Pawn => "pawn",
+        Piece::Knight => "knight",
+        Piece::Bishop => "bishop",
+        Piece::Rook => "rook",
+        Piece::Queen => "queen",
+        Piece::King => "king",
+    };
+    format!("{} {}", format!("{:?}", color).to_lowercase(), piece)
+}
+
+/// The sprites a manifest picks out of its sheet
+pub struct ThemeSprites<'a> {
+    pub light_square: Sprite<'a>,
+    pub dark_square: Sprite<'a>,
+    /// Indexed by color, then piece
+    pieces: Vec<Vec<Sprite<'a>>>,
+}
+
+impl<'a> ThemeSprites<'a> {
+    pub fn load<Loader: LoadTexture>(
+        loader: &'a Loader,
+        manifest: &ThemeManifest,
+    ) -> Result<Self, String> {
+        let sheet = SpriteSheet::load(loader, &manifest.sheet, manifest.grid_size)
+            .map_err(|e| format!("Could not load {}: {}", manifest.sheet.display(), e))?;
+        let sprite = |cell, what: &str| {
+            sheet
+                .sprite(cell)
+                .map_err(|e| format!("Theme {}: {}: {}", manifest.name, what, e))
+        };
+
+        let mut pieces = Vec::new();
+        for &color in ALL_COLORS.iter() {
+            let mut row = Vec::new();
+            for &piece in ALL_PIECES.iter() {
+                let cell = manifest.pieces.cell(color, piece);
+                row.push(sprite(cell, &piece_name(color, piece))?);
+            }
+            pieces.push(row);
+        }
+
+        Ok(Self {
+            light_square: sprite(manifest.squares.light, "light square")?,
+            dark_square: sprite(manifest.squares.dark, "dark square")?,
+            pieces,
+        })
+    }
+
+    pub fn piece(&self, piece: Piece, color: chess::Color) -> &Sprite<'a> {
+        &self.pieces[color.to_index()][piece.to_index()]
+    }
+
+    /// Every sprite: the squares, then the pieces
+    pub fn iter(&self) -> impl Iterator<Item = &Sprite<'a>> {
+        iter::once(&self.light_square)
+            .chain(iter::once(&self.dark_square))
+            .chain(self.pieces.iter().flatten())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r##"
+name = "Test"
+sheet = "sheet.png" # next to the manifest
+grid = 16
+
+[squares]
+light = [1, 0]
+dark = [0, 0]
+
+[pieces]
+white_pawn = [1, 1]
+white_knight = [1, 3]
+white_bishop = [1, 4]
+white_rook = [1, 2]
+white_queen = [1, 5]
+white_king = [1, 6]
+black_pawn = [0, 1]
+black_knight = [0, 3]
+black_bishop = [0, 4]
+black_rook = [0, 2]
+black_queen = [0, 5]
+black_king = [0, 6]
+
+[colors]
+selected = "#1e64c8"
+last_move = "#50b4ff5a"
+"##;
+
+    fn parse(text: &str) -> Result<ThemeManifest, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn valid_manifest() {
+        let manifest = parse(MANIFEST).unwrap();
+        assert_eq!(manifest.name, "Test");
+        assert_eq!(manifest.sheet, PathBuf::from("sheet.png"));
+        assert_eq!(manifest.grid_size, 16);
+        assert_eq!(manifest.squares.light, (1, 0));
+        assert_eq!(
+            manifest.pieces.cell(chess::Color::Black, Piece::Knight),
+            (0, 3)
+        );
+        assert_eq!(manifest.colors.selected, Color::RGB(0x1e, 0x64, 0xc8));
+        assert_eq!(
+            manifest.colors.last_move,
+            Color::RGBA(0x50, 0xb4, 0xff, 0x5a)
+        );
+        assert_eq!(manifest.colors.text, Theme::default().text);
+    }
+
+    #[test]
+    fn missing_piece() {
+        let error = parse(&MANIFEST.replace("white_king = [1, 6]\n", "")).unwrap_err();
+        assert!(error.contains("white_king"), "{}", error);
+    }
+
+    #[test]
+    fn unknown_key() {
+        let error =
+            parse(&MANIFEST.replace("[colors]\n", "[colors]\nshade = \"#000000\"\n")).unwrap_err();
+        assert!(error.contains("shade"), "{}", error);
+    }
+
+    #[test]
+    fn bad_color() {
+        for color in &["1e64c8", "#1e64c", "#1e64cg", "#1e64c8ff00"] {
+            let text = MANIFEST.replace("#1e64c8", color);
+            assert!(parse(&text).is_err(), "{} was accepted", color);
+        }
+    }
+}
@@ -0,0 +1,445 @@
+//! A front-end speaking the Universal Chess Interface over stdin/stdout,
+//! so the engine can be loaded into chess GUIs or played against other engines.
+
+use chess::{Board, ChessMove, MoveGen};
+
+use std::{
+    io::{self, BufRead},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    ai::{self, tablebase::Tablebase, AIState, Difficulty, SearchInfo, SearchLimits},
+    fen,
+};
+
+/// Largest transposition table that can be requested, in megabytes
+const MAX_HASH: usize = 4096;
+
+/// Reads commands from stdin until `quit` or the end of input
+pub fn run() {
+    let mut engine = Engine::default();
+
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Could not read command: {}", e);
+                break;
+            }
+        };
+
+        if !engine.handle(&line) {
+            break;
+        }
+    }
+
+    engine.stop_search();
+}
+
+/// A search running on a background thread, so that `stop` and `isready` stay responsive
+struct RunningSearch {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+#[derive(Default)]
+struct Engine {
+    ai_state: Arc<Mutex<AIState>>,
+    board: Board,
+    search: Option<RunningSearch>,
+}
+
+impl Engine {
+    /// Handles one line of input.
+    /// Returns `false` once the engine should exit.
+    fn handle(&mut self, line: &str) -> bool {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("uci") => {
+                println!(
+                    "id name {} {}",
+                    env!("CARGO_PKG_NAME"),
+                    env!("CARGO_PKG_VERSION")
+                );
+                println!("id author {}", env!("CARGO_PKG_AUTHORS"));
+                println!(
+                    "option name Hash type spin default {} min 1 max {}",
+                    ai::DEFAULT_HASH_MEGABYTES,
+                    MAX_HASH
+                );
+                println!("option name Clear Hash type button");
+                println!("option name SyzygyPath type string default <empty>");
+                let levels: Vec<_> = Difficulty::ALL
+                    .iter()
+                    .map(|level| format!(" var {}", level))
+                    .collect();
+                println!(
+                    "option name Difficulty type combo default {}{}",
+                    Difficulty::default(),
+                    levels.concat()
+                );
+                println!("option name Seed type string default <random>");
+                println!("uciok");
+            }
+            Some("isready") => println!("readyok"),
+            Some("ucinewgame") => {
+                self.stop_search();
+                self.ai_state.lock().unwrap().clear();
+                self.board = Board::default();
+            }
+            Some("position") => {
+                self.stop_search();
+                match parse_position(tokens) {
+                    Ok(board) => self.board = board,
+                    Err(e) => println!("info string {}", e),
+                }
+            }
+            Some("go") => {
+                self.stop_search();
+                self.start_search(tokens);
+            }
+            Some("stop") => self.stop_search(),
+            Some("setoption") => {
+                self.stop_search();
+                if let Err(e) = self.set_option(tokens) {
+                    println!("info string {}", e);
+                }
+            }
+            Some("quit") => return false,
+            // Blank lines and unknown commands are ignored, as the protocol asks
+            Some(command) => eprintln!("Unknown command: {}", command),
+            None => {}
+        }
+        true
+    }
+
+    /// Applies `setoption name <name> [value <value>]`, or explains why it could not
+    fn set_option<'a>(&mut self, tokens: impl Iterator<Item = &'a str>) -> Result<(), String> {
+        let tokens: Vec<_> = tokens.collect();
+        let value_start = tokens.iter().position(|&token| token == "value");
+        let name = tokens[..value_start.unwrap_or(tokens.len())]
+            .iter()
+            .skip_while(|&&token| token == "name")
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let value = value_start.map(|start| tokens[start + 1..].join(" "));
+
+        match (name.to_lowercase().as_str(), value) {
+            ("hash", Some(value)) => {
+                let megabytes = value
+                    .parse::<usize>()
+                    .map_err(|e| format!("Invalid hash size {}: {}", value, e))?;
+                let megabytes = megabytes.clamp(1, MAX_HASH);
+                self.ai_state.lock().unwrap().resize_hash(megabytes);
+            }
+            ("clear hash", _) => self.ai_state.lock().unwrap().clear(),
+            ("difficulty", Some(value)) => {
+                let difficulty = value.parse()?;
+                self.ai_state.lock().unwrap().set_difficulty(difficulty);
+            }
+            ("seed", Some(value)) if value == "<random>" => {}
+            ("seed", Some(value)) => {
+                let seed = value
+                    .parse()
+                    .map_err(|e| format!("Invalid seed {}: {}", value, e))?;
+                self.ai_state.lock().unwrap().seed(seed);
+            }
+            ("syzygypath", Some(path)) if path.is_empty() || path == "<empty>" => {
+                self.ai_state.lock().unwrap().set_tablebase(None)
+            }
+            ("syzygypath", Some(path)) => {
+                let tablebase = Tablebase::open(&path)
+                    .map_err(|e| format!("Could not open tablebases: {}", e))?;
+                println!(
+                    "info string Found {} tables of up to {} pieces in {}",
+                    tablebase.len(),
+                    tablebase.max_pieces(),
+                    path
+                );
+                let tablebase = Some(Arc::new(tablebase));
+                self.ai_state.lock().unwrap().set_tablebase(tablebase);
+            }
+            _ => return Err(format!("Unknown option {}", name)),
+        }
+        Ok(())
+    }
+
+    fn start_search<'a>(&mut self, tokens: impl Iterator<Item = &'a str>) {
+        let (limits, infinite) = parse_go(tokens, &self.board);
+        let stop = Arc::new(AtomicBool::new(false));
+        let ai_state = self.ai_state.clone();
+        let board = self.board;
+
+        let handle = {
+            let stop = stop.clone();
+            thread::spawn(move || {
+                let best_move = ai_state
+                    .lock()
+                    .unwrap()
+                    .search(board, limits, &stop, print_info);
+
+                // In infinite mode the result may only be sent after `stop`
+                while infinite && !stop.load(Ordering::Relaxed) {
+                    thread::sleep(Duration::from_millis(5));
+                }
+
+                match best_move {
+                    Some(best_move) => println!("bestmove {}", best_move),
+                    None => println!("bestmove 0000"),
+                }
+            })
+        };
+
+        self.search = Some(RunningSearch { stop, handle });
+    }
+
+    /// Stops the running search, if any, and waits for it to report its best move
+    fn stop_search(&mut self) {
+        if let Some(RunningSearch { stop, handle }) = self.search.take() {
+            stop.store(true, Ordering::Relaxed);
+            if handle.join().is_err() {
+                eprintln!("Search thread panicked");
+            }
+        }
+    }
+}
+
+fn print_info(info: &SearchInfo) {
+    let score = match ai::mate_in(info.score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", info.score),
+    };
+    let millis = info.time.as_millis();
+    let nps = u128::from(info.nodes) * 1000 / millis.max(1);
+    let pv: Vec<_> = info.pv.iter().map(ChessMove::to_string).collect();
+
+    println!(
+        "info depth {} score {} nodes {} nps {} time {} hashfull {} pv {}",
+        info.depth,
+        score,
+        info.nodes,
+        nps,
+        millis,
+        info.hashfull,
+        pv.join(" ")
+    );
+}
+
+/// Parses the arguments of `position`: `startpos` or `fen <fen>`, then optionally `moves <moves>`
+fn parse_position<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Result<Board, String> {
+    let mut board = match tokens.next() {
+        Some("startpos") => {
+            if let Some(token) = tokens.next() {
+                if token != "moves" {
+                    return Err(format!("Expected 'moves', found '{}'", token));
+                }
+            }
+            Board::default()
+        }
+        Some("fen") => {
+            let fen: Vec<_> = tokens
+                .by_ref()
+                .take_while(|&token| token != "moves")
+                .collect();
+            let fen = fen.join(" ");
+            fen::parse(&fen).map_err(|e| format!("{}: {}", e, fen))?
+        }
+        Some(token) => return Err(format!("Expected 'startpos' or 'fen', found '{}'", token)),
+        None => return Err("Missing position".to_string()),
+    };
+
+    for token in tokens {
+        let chess_move = ChessMove::from_str(token)
+            .ok()
+            .filter(|&chess_move| board.legal(chess_move))
+            .ok_or_else(|| format!("Illegal move {} in {}", token, board))?;
+        board = board.make_move_new(chess_move);
+    }
+
+    Ok(board)
+}
+
+/// Parses the arguments of `go` into search limits for the side to move on `board`.
+/// Also returns whether the search was requested in infinite mode.
+fn parse_go<'a>(mut tokens: impl Iterator<Item = &'a str>, board: &Board) -> (SearchLimits, bool) {
+    let mut limits = SearchLimits::infinite();
+    let mut infinite = false;
+    let mut clock = [None; 2];
+    let mut increment = [Duration::default(); 2];
+    let mut moves_to_go = None;
+
+    while let Some(token) = tokens.next() {
+        let mut number = || tokens.next().and_then(|value| value.parse::<u64>().ok());
+        match token {
+            "depth" => limits.depth = number().map(|depth| depth.min(u8::MAX.into()) as u8),
+            "nodes" => limits.nodes = number(),
+            "movetime" => limits.move_time = number().map(Duration::from_millis),
+            "wtime" => clock[0] = number().map(Duration::from_millis),
+            "btime" => clock[1] = number().map(Duration::from_millis),
+            "winc" => increment[0] = number().map(Duration::from_millis).unwrap_or_default(),
+            "binc" => increment[1] = number().map(Duration::from_millis).unwrap_or_default(),
+            "movestogo" => moves_to_go = number().map(|moves| moves as u32),
+            "infinite" => infinite = true,
+            _ => {}
+        }
+    }
+
+    let side = board.side_to_move().to_index();
+    if let (None, Some(remaining)) = (limits.move_time, clock[side]) {
+        limits.move_time =
+            SearchLimits::from_clock(remaining, increment[side], moves_to_go).move_time;
+    }
+
+    if MoveGen::new_legal(board).len() == 1 && !infinite {
+        // Nothing to think about
+        limits.depth = Some(1);
+    }
+
+    (limits, infinite)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(fen: &str) -> Board {
+        Board::from_str(fen).unwrap()
+    }
+
+    fn position(arguments: &str) -> Result<Board, String> {
+        parse_position(arguments.split_whitespace())
+    }
+
+    fn go(arguments: &str, board: &Board) -> SearchLimits {
+        parse_go(arguments.split_whitespace(), board).0
+    }
+
+    fn set_option(engine: &mut Engine, arguments: &str) -> Result<(), String> {
+        engine.set_option(arguments.split_whitespace())
+    }
+
+    #[test]
+    fn startpos_with_moves() {
+        assert_eq!(position("startpos"), Ok(Board::default()));
+        assert_eq!(
+            position("startpos moves e2e4 e7e5"),
+            Ok(board(
+                "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"
+            ))
+        );
+    }
+
+    #[test]
+    fn fen_with_moves() {
+        assert_eq!(
+            position("fen 4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 moves e2e4 e8d7"),
+            Ok(board("8/3k4/8/8/4P3/8/8/4K3 w - - 1 2"))
+        );
+        assert_eq!(
+            position("fen 4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"),
+            Ok(board("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"))
+        );
+    }
+
+    #[test]
+    fn bad_positions_are_rejected() {
+        assert!(position("startpos moves e2e5").is_err());
+        assert!(position("startpos moves e2e4 nonsense").is_err());
+        assert!(position("startpos e2e4").is_err());
+        assert!(position("fen 4k3/8/8/8/8/8/8/8 w - - 0 1").is_err());
+        assert!(position("somewhere").is_err());
+        assert!(position("").is_err());
+    }
+
+    #[test]
+    fn bad_moves_keep_the_position() {
+        let mut engine = Engine::default();
+        engine.handle("position startpos moves e2e4");
+        let before = engine.board;
+        assert_ne!(before, Board::default());
+
+        engine.handle("position startpos moves e2e4 e7e4");
+        assert_eq!(engine.board, before);
+        engine.handle("position startpos moves e2e4 zz");
+        assert_eq!(engine.board, before);
+    }
+
+    #[test]
+    fn go_with_clocks_budgets_the_side_to_move() {
+        let clocks = "wtime 60000 btime 30000 winc 1000 binc 500 movestogo 20";
+        let white = SearchLimits::from_clock(
+            Duration::from_millis(60000),
+            Duration::from_millis(1000),
+            Some(20),
+        );
+        let black = SearchLimits::from_clock(
+            Duration::from_millis(30000),
+            Duration::from_millis(500),
+            Some(20),
+        );
+        assert_eq!(go(clocks, &Board::default()), white);
+
+        let after_e4 = position("startpos moves e2e4").unwrap();
+        assert_eq!(go(clocks, &after_e4), black);
+        assert_ne!(white, black);
+    }
+
+    #[test]
+    fn go_with_depth_or_movetime() {
+        let limits = go("depth 5", &Board::default());
+        assert_eq!(limits.depth, Some(5));
+        assert_eq!(limits.move_time, None);
+
+        let limits = go("movetime 250", &Board::default());
+        assert_eq!(limits.move_time, Some(Duration::from_millis(250)));
+        assert_eq!(limits.depth, None);
+
+        // A fixed move time wins over the clock
+        let limits = go("wtime 60000 movetime 250", &Board::default());
+        assert_eq!(limits.move_time, Some(Duration::from_millis(250)));
+
+        assert_eq!(go("infinite", &Board::default()), SearchLimits::infinite());
+    }
+
+    #[test]
+    fn options_take_good_values() {
+        let mut engine = Engine::default();
+        assert_eq!(set_option(&mut engine, "name Hash value 1"), Ok(()));
+        // Out of range sizes are clamped rather than refused
+        assert_eq!(set_option(&mut engine, "name Hash value 0"), Ok(()));
+
+        assert_eq!(
+            set_option(&mut engine, "name Difficulty value easy"),
+            Ok(())
+        );
+        assert_eq!(
+            engine.ai_state.lock().unwrap().difficulty(),
+            Difficulty::Easy
+        );
+
+        assert_eq!(set_option(&mut engine, "name Seed value 42"), Ok(()));
+        assert_eq!(set_option(&mut engine, "name Seed value <random>"), Ok(()));
+    }
+
+    #[test]
+    fn options_refuse_bad_values() {
+        let mut engine = Engine::default();
+        assert!(set_option(&mut engine, "name Hash value lots").is_err());
+        assert!(set_option(&mut engine, "name Hash value -1").is_err());
+        assert!(set_option(&mut engine, "name Difficulty value impossible").is_err());
+        assert_eq!(
+            engine.ai_state.lock().unwrap().difficulty(),
+            Difficulty::default()
+        );
+        assert!(set_option(&mut engine, "name Seed value forty-two").is_err());
+        assert!(set_option(&mut engine, "name Ponder value true").is_err());
+    }
+}